@@ -0,0 +1,497 @@
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum, ValueHint};
+use clap_complete::Shell;
+use glob::Pattern;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::config::{parse_size, parse_whitespace_arg, DEFAULT_BACKUP_SUFFIX};
+use crate::regions::Region;
+use crate::rules::RulesEntry;
+use crate::rules::parse_whitespace_char;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  nothing needed trimming
+  1  files were modified, or check/diff mode found trailing whitespaces
+  2  invalid arguments or configuration
+  3  git error
+  4  IO error
+  5  the repository is bare, only --check and check-commit work there";
+
+#[derive(Clone, Parser)]
+#[command(version, about, after_help = EXIT_CODES, disable_help_subcommand = true)]
+#[command(group = ArgGroup::new("untracked_modes").args(["working_tree", "all"]))]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Only report lines with trailing whitespaces, don't modify any files, the default in CI environments
+    #[arg(long)]
+    pub check: bool,
+
+    /// Rewrite files even if check mode is configured or a CI environment is detected
+    #[arg(long, conflicts_with_all = ["check", "diff"])]
+    pub fix: bool,
+
+    /// Don't switch to check mode in CI environments
+    #[arg(long)]
+    pub no_ci_detect: bool,
+
+    /// Print the changes as a unified diff instead of modifying any files
+    #[arg(long, conflicts_with = "format")]
+    pub diff: bool,
+
+    /// Trim every line of the given files and directories, without git
+    #[arg(long, conflicts_with_all = ["no_add", "interactive", "paths_from_stdin"])]
+    pub files: bool,
+
+    /// Don't rewrite files larger than SIZE, like 500K or 5M (default), 0 disables the limit
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_file_size: Option<u64>,
+
+    /// Copy a file to <FILE><SUFFIX> before it is rewritten
+    #[arg(long, conflicts_with = "index_only")]
+    pub backup: bool,
+
+    /// Suffix of the --backup copies
+    #[arg(long, value_name = "SUFFIX", default_value = DEFAULT_BACKUP_SUFFIX)]
+    pub backup_suffix: String,
+
+    /// Process the repository at PATH instead of the one of the current directory, can be repeated
+    #[arg(long = "repo", value_name = "PATH", value_hint = ValueHint::DirPath, conflicts_with = "files")]
+    pub repos: Vec<PathBuf>,
+
+    /// Check or fix the lines added by a unified diff read from FILE or stdin (`-`) instead of the staged changes,
+    /// the fixed files are not staged
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = ["files", "all", "working_tree", "index_only", "interactive", "paths_from_stdin", "against", "amend", "repos"]
+    )]
+    pub patch: Option<PathBuf>,
+
+    /// Diff the index against this revision instead of HEAD
+    #[arg(long, value_name = "REV", conflicts_with = "files")]
+    pub against: Option<String>,
+
+    /// Diff the index against HEAD's parent to cover the whole commit when running for `git commit --amend`
+    #[arg(long, conflicts_with_all = ["against", "files"])]
+    pub amend: bool,
+
+    /// Trim the staged content in the index only, the files in the working tree are left untouched
+    #[arg(long, conflicts_with_all = ["files", "no_add", "working_tree"])]
+    pub index_only: bool,
+
+    /// Also trim the files with unstaged changes, which are skipped otherwise: their staged content in the index
+    /// and their working tree file only on the lines that weren't changed since they were staged
+    #[arg(long)]
+    pub force_dirty: bool,
+
+    /// Also trim the files added with `git add -N`, which are skipped otherwise: their whole working tree file is
+    /// trimmed and they stay intent-to-add
+    #[arg(long)]
+    pub include_intent_to_add: bool,
+
+    /// Trim the unstaged changes in the working tree instead of the staged ones, nothing is staged
+    #[arg(long, conflicts_with_all = ["against", "amend", "files", "no_add"])]
+    pub working_tree: bool,
+
+    /// Also process the staged changes of every initialized submodule
+    #[arg(long, conflicts_with = "files")]
+    pub recurse_submodules: bool,
+
+    /// Also trim the untracked files that are not ignored with --working-tree or --all, they stay untracked
+    #[arg(long, requires = "untracked_modes")]
+    pub include_untracked: bool,
+
+    /// Trim every tracked file, not only the staged changes
+    #[arg(long, conflicts_with_all = ["against", "amend", "files", "working_tree"])]
+    pub all: bool,
+
+    /// Trim every line of the changed files, not only the changed lines
+    #[arg(long)]
+    pub whole_file: bool,
+
+    /// Which checks to apply in git's core.whitespace syntax like `-blank-at-eof,trailing-space`,
+    /// overrides core.whitespace
+    #[arg(long, value_name = "RULES", value_parser = parse_whitespace_arg)]
+    pub whitespace: Option<String>,
+
+    /// Whether trailing whitespaces are trimmed, turned off by `-blank-at-eol`
+    #[arg(skip = true)]
+    pub blank_at_eol: bool,
+
+    /// Treat files matching the glob as text even if they look binary, can be repeated
+    #[arg(long, value_name = "GLOB")]
+    pub force_text: Vec<Pattern>,
+
+    /// Also trim Unicode spaces like U+00A0 NO-BREAK SPACE or U+3000 IDEOGRAPHIC SPACE, not only spaces and tabs
+    #[arg(long)]
+    pub unicode_whitespace: bool,
+
+    /// The characters --unicode-whitespace trims instead of every Unicode space, as characters or U+XXXX
+    #[arg(long, value_name = "CHARS", value_delimiter = ',', value_parser = parse_whitespace_char)]
+    pub whitespace_chars: Vec<char>,
+
+    /// Also trim vertical tabs, form feeds and carriage returns that aren't part of a CRLF line ending
+    #[arg(long)]
+    pub control_whitespace: bool,
+
+    /// Keep the whitespaces of lines that have nothing else, e.g. the indentation editors keep on blank lines
+    #[arg(long)]
+    pub keep_blank_line_indent: bool,
+
+    /// Also remove the blank lines added at the end of a file, the last line keeps its newline
+    #[arg(long)]
+    pub trim_eof_blank_lines: bool,
+
+    /// Also squeeze the runs of more than N blank lines the change adds down to N lines, the blank lines that
+    /// were already there are kept [default: 1]
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub squeeze_blank_lines: Option<u32>,
+
+    /// Also terminate a last line without newline, with the line ending most lines of the file use
+    #[arg(long)]
+    pub ensure_final_newline: bool,
+
+    /// Keep two trailing spaces after text in Markdown files as a hard line break, more are trimmed down to two
+    #[arg(long)]
+    pub markdown_breaks: bool,
+
+    /// More extensions of Markdown files for --markdown-breaks, besides md and markdown
+    #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+    pub markdown_ext: Vec<String>,
+
+    /// Keep the trailing whitespaces inside Markdown code fences and YAML block scalars,
+    /// the `regions` table of .rtrim.toml assigns the rules to more extensions
+    #[arg(long)]
+    pub protect_regions: bool,
+
+    /// The `regions` table of the configuration
+    #[arg(skip)]
+    pub regions: BTreeMap<String, Region>,
+
+    /// The `rules` table of the configuration
+    #[arg(skip)]
+    pub file_rules: Vec<RulesEntry>,
+
+    /// Also flag spaces before a tab in the indentation, they are removed or turned into tabs
+    #[arg(long)]
+    pub space_before_tab: bool,
+
+    /// Also flag tabs in the indentation, Makefiles, Go and TSV files keep theirs
+    #[arg(long)]
+    pub tab_in_indent: bool,
+
+    /// Expand the tabs in the indentation of the flagged lines to spaces with tab stops every N columns,
+    /// implies --tab-in-indent
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub fix_tabs: Option<u32>,
+
+    /// Also flag indentations with a tab width of spaces, YAML files keep theirs
+    #[arg(long, conflicts_with_all = ["tab_in_indent", "fix_tabs"])]
+    pub indent_with_non_tab: bool,
+
+    /// Turn the spaces in the indentation of the flagged lines into tabs, alignment spaces after the last tab stop
+    /// are kept, implies --indent-with-non-tab
+    #[arg(long, conflicts_with_all = ["tab_in_indent", "fix_tabs"])]
+    pub fix_spaces: bool,
+
+    /// Convert every line ending of the flagged files, files with the `-text` attribute keep theirs
+    #[arg(long, value_name = "EOL")]
+    pub eol: Option<Eol>,
+
+    /// Also report the added lines that end differently than the rest of the file, --eol converts them
+    #[arg(long)]
+    pub mixed_line_endings: bool,
+
+    /// Also report invisible characters like U+200B ZERO WIDTH SPACE or U+00AD SOFT HYPHEN at the end of lines
+    #[arg(long)]
+    pub detect_invisible: bool,
+
+    /// The characters --detect-invisible reports instead of the default ones, as characters or U+XXXX
+    #[arg(long, value_name = "CHARS", value_delimiter = ',', value_parser = parse_whitespace_char)]
+    pub invisible_chars: Vec<char>,
+
+    /// Remove the invisible characters at the end of the flagged lines, implies --detect-invisible
+    #[arg(long)]
+    pub fix_invisible: bool,
+
+    /// Follow the trim_trailing_whitespace, insert_final_newline and end_of_line properties of .editorconfig,
+    /// the command line and rtrim's configuration win
+    #[arg(long)]
+    pub editorconfig: bool,
+
+    /// Also trim UTF-16 files starting with a BOM, they are decoded and written back in their byte order with the BOM.
+    /// Files that aren't valid UTF-16 are skipped
+    #[arg(long)]
+    pub utf16: bool,
+
+    /// The columns between tab stops for the indentation checks [default: 8]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub tab_width: Option<u32>,
+
+    /// Trim the files in the working tree but don't stage the result
+    #[arg(long)]
+    pub no_add: bool,
+
+    /// Ask before each file is trimmed
+    #[arg(short, long, conflicts_with_all = ["check", "diff"])]
+    pub interactive: bool,
+
+    #[command(flatten)]
+    pub file_filters: FileFilters,
+
+    /// Read the pathspecs from stdin, one per line
+    #[arg(long, conflicts_with_all = ["path_filters", "interactive"])]
+    pub paths_from_stdin: bool,
+
+    /// Print the trimmed content of the single given file to stdout, leaving the working tree and index untouched
+    #[arg(long, conflicts_with_all = ["diff", "check", "files", "patch", "list_files", "index_only", "interactive", "repos"])]
+    pub stdout: bool,
+
+    /// Print the number of trimmed lines per file like `git diff --stat`
+    #[arg(long, conflicts_with = "list_files")]
+    pub stat: bool,
+
+    /// Print only the paths of the modified files, in check mode of the files with trailing whitespaces
+    #[arg(long, conflicts_with_all = ["format", "diff"])]
+    pub list_files: bool,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath, conflicts_with = "diff")]
+    pub report_file: Option<PathBuf>,
+
+    /// Process exactly the files listed in FILE, one path per line relative to the repository root,
+    /// without pathspec matching
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, conflicts_with_all = ["path_filters", "paths_from_stdin", "files", "patch"])]
+    pub file_list: Option<PathBuf>,
+
+    /// Fail if a file of --file-list exists neither in the index nor in the working tree
+    #[arg(long, requires = "file_list")]
+    pub require_listed: bool,
+
+    /// Use NUL instead of newline to delimit paths read with --paths-from-stdin and --file-list and printed with --list-files
+    #[arg(short = 'z')]
+    pub null_delimited: bool,
+
+    /// Print the effective configuration after merging git config, .rtrim.toml and the command line
+    #[arg(long)]
+    pub show_config: bool,
+
+    /// Print each rewritten file and the trimmed line numbers to stderr
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Suppress all output except errors, wins over --verbose
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Don't show progress counters, they are only shown if stderr is a terminal
+    #[arg(long, global = true)]
+    pub no_progress: bool,
+
+    /// Log what rtrim decides to stderr (off, error, warn, info, debug, trace), overrides RTRIM_LOG
+    #[arg(long, value_name = "LEVEL", global = true)]
+    pub log_level: Option<log::LevelFilter>,
+
+    /// Highlight the trailing whitespaces in check and diff output
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorWhen::Auto)]
+    pub color: ColorWhen,
+
+    /// Output format of the report
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Severity of the diagnostics of --format rdjson
+    #[arg(long, value_enum, default_value_t = Severity::Warning)]
+    pub severity: Severity,
+
+    /// Restrict the staged files to the given git pathspecs, with --files the files and directories to trim.
+    /// Everything after `--` is a pathspec, also when it starts with a dash
+    #[arg(value_name = "PATHSPEC", value_hint = ValueHint::AnyPath)]
+    pub path_filters: Vec<String>,
+}
+
+impl Cli {
+    /// The suffix of backup copies if --backup is given.
+    pub fn backup_suffix(&self) -> Option<&str> {
+        self.backup.then_some(self.backup_suffix.as_str())
+    }
+}
+
+#[derive(Clone, Subcommand)]
+pub enum Command {
+    /// Trim every line read from stdin and write it to stdout, usable as a git clean filter
+    Filter {
+        #[command(flatten)]
+        file_filters: FileFilters,
+
+        /// Path of the filtered file (`%f`), used to match --exclude, --ext and --skip-ext
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: Option<PathBuf>,
+    },
+    /// Check the lines added by the commits of a revision range, merge commits are skipped
+    CheckRange {
+        /// A range like `origin/main..HEAD`, a single revision checks its whole history
+        range: String,
+    },
+    /// Check the lines added by single commits compared to their first parent, works in bare repositories
+    CheckCommit {
+        /// The commits to check
+        #[arg(required = true, value_name = "REV")]
+        revs: Vec<String>,
+    },
+    /// Check the commits about to be pushed, to be run from a pre-push hook
+    PrePush {
+        /// Name of the remote, passed by git as the first hook argument
+        remote: Option<String>,
+        /// URL of the remote, passed by git as the second hook argument
+        url: Option<String>,
+    },
+    /// Trim the commit message file, to be run from a commit-msg hook
+    CommitMsg {
+        /// The message file, passed by git as the first hook argument
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+
+        /// Also trim the comment lines starting with `core.commentChar`, they are left untouched by default
+        #[arg(long)]
+        trim_comments: bool,
+    },
+    /// Trim the lines added by HEAD and amend it, for `git rebase -x 'rtrim fix-head'`
+    FixHead,
+    /// Rewrite the commits of a range with the trailing whitespaces they added removed
+    Rewrite {
+        /// A range like `main..feature`
+        range: String,
+
+        #[command(flatten)]
+        file_filters: FileFilters,
+
+        /// Name of the new branch, `<tip>-trimmed` by default
+        #[arg(long, value_name = "NAME")]
+        branch: Option<String>,
+
+        /// Move the branch of the range's tip instead of creating a new one
+        #[arg(long, conflicts_with = "branch")]
+        force: bool,
+    },
+    /// Watch the working tree and trim the added lines of tracked files whenever they are written, until Ctrl-C
+    Watch {
+        /// Milliseconds to wait for further changes before trimming
+        #[arg(long, value_name = "MS", default_value_t = 300)]
+        debounce: u64,
+    },
+    /// Diagnose the repository, hook and configuration rtrim would use
+    Doctor,
+    /// Print a shell completion script to stdout
+    Completions {
+        shell: Shell,
+    },
+    /// Manage the pre-commit hook
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+}
+
+#[derive(Clone, Subcommand)]
+pub enum HookAction {
+    /// Add rtrim to the pre-commit hook, honors core.hooksPath
+    Install {
+        /// Append to an existing pre-commit hook that doesn't call rtrim yet
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove the lines added by install from the pre-commit hook
+    Uninstall,
+    /// Show whether the pre-commit hook calls rtrim
+    Status,
+}
+
+#[derive(Clone, Args)]
+pub struct FileFilters {
+    /// Skip files matching the glob, can be repeated. Wins over pathspecs
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<Pattern>,
+
+    /// Only process files with these extensions, `none` matches files without extension
+    #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+    pub ext: Vec<String>,
+
+    /// Skip files with these extensions, `none` matches files without extension
+    #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',')]
+    pub skip_ext: Vec<String>,
+
+    /// Also process patch files (.patch, .diff and .rej), whose trailing whitespaces are significant
+    #[arg(long)]
+    pub no_default_excludes: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+    /// GitHub Actions workflow commands
+    Github,
+    /// SARIF 2.1.0 log for code scanning
+    Sarif,
+    /// Checkstyle XML
+    Checkstyle,
+    /// reviewdog diagnostic JSON with suggested fixes
+    Rdjson,
+}
+
+/// The line ending `--eol` converts to.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Eol {
+    Lf,
+    Crlf,
+    /// CRLF on Windows, LF elsewhere
+    Native,
+}
+
+impl Eol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Eol::Lf => "\n",
+            Eol::Crlf => "\r\n",
+            Eol::Native if cfg!(windows) => "\r\n",
+            Eol::Native => "\n",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    /// The reviewdog name of the severity.
+    pub fn as_rdjson(&self) -> &'static str {
+        match self {
+            Severity::Error => "ERROR",
+            Severity::Warning => "WARNING",
+            Severity::Info => "INFO",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorWhen {
+    Always,
+    Never,
+    Auto,
+}
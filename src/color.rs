@@ -0,0 +1,49 @@
+use std::env;
+use std::io::{self, IsTerminal};
+
+use crate::cli::ColorWhen;
+use crate::rules::Rules;
+
+pub const BOLD: &str = "\x1b[1m";
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const CYAN: &str = "\x1b[36m";
+pub const RED_BACKGROUND: &str = "\x1b[41m";
+const RESET: &str = "\x1b[m";
+
+/// `auto` colorizes if stdout is a terminal and `NO_COLOR` is not set, see https://no-color.org
+pub fn enabled(when: ColorWhen) -> bool {
+    match when {
+        ColorWhen::Always => true,
+        ColorWhen::Never => false,
+        ColorWhen::Auto => {
+            env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+        }
+    }
+}
+
+pub fn paint(text: &str, color: &str, enabled: bool) -> String {
+    if enabled && !text.is_empty() {
+        format!("{}{}{}", color, text, RESET)
+    } else {
+        String::from(text)
+    }
+}
+
+/// Like `paint` for content that isn't necessarily valid UTF-8.
+pub fn paint_bytes(text: &[u8], color: &str, enabled: bool) -> Vec<u8> {
+    if enabled && !text.is_empty() {
+        [color.as_bytes(), text, RESET.as_bytes()].concat()
+    } else {
+        text.to_vec()
+    }
+}
+
+/// Highlights the whitespaces `rules` trim from `line` with a red background, like `git diff` does.
+pub fn highlight_whitespaces(line: &str, rules: &Rules, enabled: bool) -> String {
+    // only whole characters are trimmed, so the end is a character boundary
+    let end = rules.trim(line.as_bytes()).len();
+
+    format!("{}{}", &line[..end], paint(&line[end..], RED_BACKGROUND, enabled))
+}
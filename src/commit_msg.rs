@@ -0,0 +1,91 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use git2::Repository;
+
+use crate::error::RTrimError;
+use crate::output::Output;
+use crate::report::plural;
+use crate::trim::{split_line_ending, trim_end_bytes};
+use crate::Outcome;
+
+/// The comment character of git's `core.commentChar`, `#` outside a repository or for `auto`.
+fn comment_char() -> String {
+    let configured = Repository::open_from_env()
+        .and_then(|repo| repo.config())
+        .and_then(|config| config.get_string("core.commentChar"));
+
+    match configured {
+        Ok(value) if !value.is_empty() && value != "auto" => value,
+        _ => String::from("#"),
+    }
+}
+
+/// Trims every line of a commit message, comment lines are kept unless `trim_comments`.
+/// The final newline, or its absence, is kept. Returns the number of trimmed lines.
+fn trim_message<W: Write>(
+    message: &[u8],
+    comment: &[u8],
+    trim_comments: bool,
+    writer: &mut W,
+) -> Result<usize, io::Error> {
+    let mut trimmed = 0;
+
+    for line in message.split_inclusive(|b| *b == b'\n') {
+        let (content, line_ending) = split_line_ending(line);
+        let new_content = if !trim_comments && content.starts_with(comment) {
+            content
+        } else {
+            trim_end_bytes(content)
+        };
+
+        if new_content.len() != content.len() {
+            trimmed += 1;
+        }
+
+        writer.write_all(new_content)?;
+        writer.write_all(line_ending)?;
+    }
+
+    Ok(trimmed)
+}
+
+/// Replaces `path` with `content` through a temporary file next to it.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), io::Error> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".rtrim");
+    let temp_path = PathBuf::from(temp_name);
+
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .and_then(|mut file| file.write_all(content).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    if result.is_err() {
+        _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+/// `commit-msg` subcommand: trims the message file git passes to the commit-msg hook.
+pub fn run_commit_msg(path: &Path, trim_comments: bool, out: &Output) -> Result<Outcome, RTrimError> {
+    let with_path = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", path.display(), e));
+
+    let message = fs::read(path).map_err(with_path)?;
+    let mut trimmed_message = Vec::with_capacity(message.len());
+    let trimmed = trim_message(&message, comment_char().as_bytes(), trim_comments, &mut trimmed_message)?;
+
+    if trimmed == 0 {
+        return Ok(Outcome::Clean);
+    }
+
+    write_atomically(path, &trimmed_message).map_err(with_path)?;
+    out.info(format_args!("rtrim: trimmed {} of the commit message", plural(trimmed, "line")));
+
+    // the commit goes on, a non-zero exit code would abort it
+    Ok(Outcome::Clean)
+}
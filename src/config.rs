@@ -0,0 +1,487 @@
+use clap::ValueEnum;
+use git2::ConfigLevel;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::cli::{Cli, Eol};
+use crate::error::RTrimError;
+use crate::output::Output;
+use crate::regions::Region;
+use crate::rules::{code_point, parse_whitespace_char, FileRules, RulesEntry};
+
+pub const CONFIG_FILE_NAME: &str = ".rtrim.toml";
+pub const DEFAULT_BACKUP_SUFFIX: &str = ".orig";
+pub const DEFAULT_MAX_FILE_SIZE: &str = "5M";
+
+/// Defaults for the command-line flags, every layer only sets the keys it knows about.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Only report, don't modify any files.
+    pub check: Option<bool>,
+    /// Stage the trimmed files.
+    pub add: Option<bool>,
+    pub exclude: Option<Vec<String>>,
+    pub ext: Option<Vec<String>>,
+    pub skip_ext: Option<Vec<String>>,
+    /// Skip patch files unless `ext` lists their extension.
+    pub default_excludes: Option<bool>,
+    /// Trim every line of the changed files.
+    pub whole_file: Option<bool>,
+    /// Trim the files with unstaged changes too.
+    pub force_dirty: Option<bool>,
+    /// Trim the files added with `git add -N` too.
+    pub include_intent_to_add: Option<bool>,
+    /// Copy files before they are rewritten.
+    pub backup: Option<bool>,
+    pub backup_suffix: Option<String>,
+    /// Larger files are not rewritten, `0` disables the limit.
+    pub max_file_size: Option<String>,
+    /// Trim the whitespaces at the end of lines, git's `blank-at-eol`.
+    pub blank_at_eol: Option<bool>,
+    /// Also trim Unicode spaces.
+    pub unicode_whitespace: Option<bool>,
+    /// The characters trimmed by `unicode-whitespace`, as characters or `U+XXXX`.
+    pub whitespace_chars: Option<Vec<String>>,
+    /// Also trim vertical tabs, form feeds and stray carriage returns.
+    pub control_whitespace: Option<bool>,
+    /// Keep the whitespaces of whitespace-only lines.
+    pub keep_blank_line_indent: Option<bool>,
+    /// Remove the blank lines added at the end of a file.
+    pub trim_eof_blank_lines: Option<bool>,
+    /// Squeeze the runs of added blank lines longer than this.
+    pub squeeze_blank_lines: Option<u32>,
+    /// Add a missing newline at the end of a file.
+    pub ensure_final_newline: Option<bool>,
+    /// Keep the hard line breaks of Markdown files.
+    pub markdown_breaks: Option<bool>,
+    /// More extensions of Markdown files.
+    pub markdown_ext: Option<Vec<String>>,
+    /// Keep the trailing whitespaces in protected regions.
+    pub protect_regions: Option<bool>,
+    /// Flag the spaces before a tab in the indentation.
+    pub space_before_tab: Option<bool>,
+    /// The columns between tab stops.
+    pub tab_width: Option<u32>,
+    /// Flag the tabs in the indentation.
+    pub tab_in_indent: Option<bool>,
+    /// Expand the tabs in the indentation to tab stops every this many columns.
+    pub fix_tabs: Option<u32>,
+    /// The line ending every line is converted to, `lf`, `crlf` or `native`.
+    pub eol: Option<String>,
+    /// Report the lines that end differently than the rest of the file.
+    pub mixed_line_endings: Option<bool>,
+    /// Report invisible characters at the end of lines.
+    pub detect_invisible: Option<bool>,
+    /// The characters reported by `detect-invisible`, as characters or `U+XXXX`.
+    pub invisible_chars: Option<Vec<String>>,
+    /// Remove the invisible characters at the end of lines.
+    pub fix_invisible: Option<bool>,
+    /// Follow the `.editorconfig` files.
+    pub editorconfig: Option<bool>,
+    /// Trim UTF-16 files with a BOM.
+    pub utf16: Option<bool>,
+    /// Flag the indentations with a tab width of spaces.
+    pub indent_with_non_tab: Option<bool>,
+    /// Turn the spaces in the indentation into tabs.
+    pub fix_spaces: Option<bool>,
+    /// The protected regions per extension, in addition to the defaults.
+    pub regions: Option<BTreeMap<String, Region>>,
+    /// Settings per extension like `md` or glob like `docs/**`, the most specific entry wins.
+    pub rules: Option<BTreeMap<String, FileRules>>,
+}
+
+impl Config {
+    /// Reads `.rtrim.toml` from the repository root, a missing file is an empty config.
+    pub fn load(workdir: &Path) -> Result<Self, RTrimError> {
+        let path = workdir.join(CONFIG_FILE_NAME);
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(RTrimError::Io(e)),
+        };
+
+        // the error names the line and the offending key
+        toml::from_str(&content).map_err(|e| RTrimError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Reads the `rtrim.*` keys from git config, returns the global (system, XDG and global files)
+    /// and the repository local values separately. Unknown keys are ignored with a warning.
+    pub fn from_git(git_config: &git2::Config, out: &Output) -> Result<(Self, Self), RTrimError> {
+        let mut entries: Vec<(String, String, ConfigLevel)> = Vec::new();
+
+        git_config.entries(Some("rtrim.*"))?.for_each(|entry| {
+            if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+                entries.push((name.to_lowercase(), value.to_string(), entry.level()));
+            }
+        })?;
+
+        // lower levels first, so that later values override earlier ones
+        entries.sort_by_key(|(_, _, level)| *level as i32);
+
+        let mut global = Config::default();
+        let mut local = Config::default();
+
+        for (name, value, level) in entries {
+            let config = if (level as i32) < (ConfigLevel::Local as i32) {
+                &mut global
+            } else {
+                &mut local
+            };
+
+            let parse_bool = |value: &str| {
+                git2::Config::parse_bool(value)
+                    .map_err(|_| RTrimError::Config(format!("git config {}: invalid boolean `{}`", name, value)))
+            };
+
+            match name.as_str() {
+                "rtrim.check" => config.check = Some(parse_bool(&value)?),
+                "rtrim.add" => config.add = Some(parse_bool(&value)?),
+                "rtrim.noadd" => config.add = Some(!parse_bool(&value)?),
+                "rtrim.exclude" => config.exclude.get_or_insert_with(Vec::new).push(value),
+                "rtrim.ext" => config.ext.get_or_insert_with(Vec::new).extend(split_list(&value)),
+                "rtrim.skipext" => config.skip_ext.get_or_insert_with(Vec::new).extend(split_list(&value)),
+                "rtrim.defaultexcludes" => config.default_excludes = Some(parse_bool(&value)?),
+                "rtrim.wholefile" => config.whole_file = Some(parse_bool(&value)?),
+                "rtrim.forcedirty" => config.force_dirty = Some(parse_bool(&value)?),
+                "rtrim.includeintenttoadd" => config.include_intent_to_add = Some(parse_bool(&value)?),
+                "rtrim.backup" => config.backup = Some(parse_bool(&value)?),
+                "rtrim.backupsuffix" => config.backup_suffix = Some(value),
+                "rtrim.maxfilesize" => config.max_file_size = Some(value),
+                "rtrim.blankateol" => config.blank_at_eol = Some(parse_bool(&value)?),
+                "rtrim.unicodewhitespace" => config.unicode_whitespace = Some(parse_bool(&value)?),
+                "rtrim.controlwhitespace" => config.control_whitespace = Some(parse_bool(&value)?),
+                "rtrim.keepblanklineindent" => config.keep_blank_line_indent = Some(parse_bool(&value)?),
+                "rtrim.trimeofblanklines" => config.trim_eof_blank_lines = Some(parse_bool(&value)?),
+                "rtrim.squeezeblanklines" => {
+                    config.squeeze_blank_lines = Some(value.parse().map_err(|_| {
+                        RTrimError::Config(format!("git config {}: invalid number of lines `{}`", name, value))
+                    })?)
+                }
+                "rtrim.ensurefinalnewline" => config.ensure_final_newline = Some(parse_bool(&value)?),
+                "rtrim.markdownbreaks" => config.markdown_breaks = Some(parse_bool(&value)?),
+                "rtrim.markdownext" => config.markdown_ext.get_or_insert_with(Vec::new).extend(split_list(&value)),
+                "rtrim.protectregions" => config.protect_regions = Some(parse_bool(&value)?),
+                "rtrim.spacebeforetab" => config.space_before_tab = Some(parse_bool(&value)?),
+                "rtrim.tabinindent" => config.tab_in_indent = Some(parse_bool(&value)?),
+                "rtrim.eol" => config.eol = Some(value),
+                "rtrim.mixedlineendings" => config.mixed_line_endings = Some(parse_bool(&value)?),
+                "rtrim.detectinvisible" => config.detect_invisible = Some(parse_bool(&value)?),
+                "rtrim.invisiblechars" => {
+                    config.invisible_chars.get_or_insert_with(Vec::new).extend(split_list(&value))
+                }
+                "rtrim.fixinvisible" => config.fix_invisible = Some(parse_bool(&value)?),
+                "rtrim.editorconfig" => config.editorconfig = Some(parse_bool(&value)?),
+                "rtrim.utf16" => config.utf16 = Some(parse_bool(&value)?),
+                "rtrim.indentwithnontab" => config.indent_with_non_tab = Some(parse_bool(&value)?),
+                "rtrim.fixspaces" => config.fix_spaces = Some(parse_bool(&value)?),
+                "rtrim.fixtabs" => {
+                    config.fix_tabs = Some(value.parse().map_err(|_| {
+                        RTrimError::Config(format!("git config {}: invalid tab width `{}`", name, value))
+                    })?)
+                }
+                "rtrim.tabwidth" => {
+                    config.tab_width = Some(value.parse().map_err(|_| {
+                        RTrimError::Config(format!("git config {}: invalid tab width `{}`", name, value))
+                    })?)
+                }
+                "rtrim.whitespacechars" => {
+                    config.whitespace_chars.get_or_insert_with(Vec::new).extend(split_list(&value))
+                }
+                _ => out.verbose(format_args!("warning unknown git config key {} is ignored", name)),
+            }
+        }
+
+        Ok((global, local))
+    }
+
+    /// The checks `core.whitespace` enables, starting from git's defaults. Empty if it isn't set.
+    /// Unknown rules are ignored with a warning like git does.
+    pub fn from_core_whitespace(git_config: &git2::Config, out: &Output) -> Result<Self, RTrimError> {
+        let value = match git_config.get_string("core.whitespace") {
+            Ok(value) => value,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(RTrimError::Git(e)),
+        };
+
+        let mut config = Config {
+            blank_at_eol: Some(true),
+            trim_eof_blank_lines: Some(true),
+            space_before_tab: Some(true),
+            ..Config::default()
+        };
+        for rule in parse_whitespace_rules(&value, &mut config) {
+            out.warning(format_args!("core.whitespace: unknown rule `{}` is ignored", rule));
+        }
+
+        Ok(config)
+    }
+
+    /// The values given on the command line.
+    pub fn from_cli(cli: &Cli) -> Self {
+        let filters = &cli.file_filters;
+
+        let mut whitespace_rules = Config::default();
+        if let Some(rules) = &cli.whitespace {
+            parse_whitespace_rules(rules, &mut whitespace_rules);
+        }
+
+        Config {
+            check: cli.check.then_some(true),
+            add: cli.no_add.then_some(false),
+            exclude: (!filters.exclude.is_empty())
+                .then(|| filters.exclude.iter().map(|p| p.as_str().to_string()).collect()),
+            ext: (!filters.ext.is_empty()).then(|| filters.ext.clone()),
+            skip_ext: (!filters.skip_ext.is_empty()).then(|| filters.skip_ext.clone()),
+            default_excludes: filters.no_default_excludes.then_some(false),
+            whole_file: cli.whole_file.then_some(true),
+            force_dirty: cli.force_dirty.then_some(true),
+            include_intent_to_add: cli.include_intent_to_add.then_some(true),
+            backup: cli.backup.then_some(true),
+            backup_suffix: (cli.backup_suffix != DEFAULT_BACKUP_SUFFIX).then(|| cli.backup_suffix.clone()),
+            max_file_size: cli.max_file_size.map(|size| size.to_string()),
+            blank_at_eol: whitespace_rules.blank_at_eol,
+            unicode_whitespace: cli.unicode_whitespace.then_some(true),
+            whitespace_chars: (!cli.whitespace_chars.is_empty())
+                .then(|| cli.whitespace_chars.iter().map(|c| code_point(*c)).collect()),
+            control_whitespace: cli.control_whitespace.then_some(true),
+            keep_blank_line_indent: cli.keep_blank_line_indent.then_some(true),
+            trim_eof_blank_lines: cli.trim_eof_blank_lines.then_some(true).or(whitespace_rules.trim_eof_blank_lines),
+            squeeze_blank_lines: cli.squeeze_blank_lines,
+            ensure_final_newline: cli.ensure_final_newline.then_some(true),
+            markdown_breaks: cli.markdown_breaks.then_some(true),
+            markdown_ext: (!cli.markdown_ext.is_empty()).then(|| cli.markdown_ext.clone()),
+            protect_regions: cli.protect_regions.then_some(true),
+            space_before_tab: cli.space_before_tab.then_some(true).or(whitespace_rules.space_before_tab),
+            tab_width: cli.tab_width.or(whitespace_rules.tab_width),
+            tab_in_indent: cli.tab_in_indent.then_some(true).or(whitespace_rules.tab_in_indent),
+            fix_tabs: cli.fix_tabs,
+            eol: cli.eol.and_then(|eol| eol.to_possible_value()).map(|value| value.get_name().to_string()),
+            mixed_line_endings: cli.mixed_line_endings.then_some(true),
+            detect_invisible: cli.detect_invisible.then_some(true),
+            invisible_chars: (!cli.invisible_chars.is_empty())
+                .then(|| cli.invisible_chars.iter().map(|c| code_point(*c)).collect()),
+            fix_invisible: cli.fix_invisible.then_some(true),
+            editorconfig: cli.editorconfig.then_some(true),
+            utf16: cli.utf16.then_some(true),
+            indent_with_non_tab: cli.indent_with_non_tab.then_some(true).or(whitespace_rules.indent_with_non_tab),
+            fix_spaces: cli.fix_spaces.then_some(true),
+            regions: (!cli.regions.is_empty()).then(|| cli.regions.clone()),
+            rules: (!cli.file_rules.is_empty())
+                .then(|| cli.file_rules.iter().map(|entry| (entry.key.clone(), entry.rules.clone())).collect()),
+        }
+    }
+
+    /// Keys set in `other` win.
+    pub fn merge(self, other: Config) -> Self {
+        Config {
+            check: other.check.or(self.check),
+            add: other.add.or(self.add),
+            exclude: other.exclude.or(self.exclude),
+            ext: other.ext.or(self.ext),
+            skip_ext: other.skip_ext.or(self.skip_ext),
+            default_excludes: other.default_excludes.or(self.default_excludes),
+            whole_file: other.whole_file.or(self.whole_file),
+            force_dirty: other.force_dirty.or(self.force_dirty),
+            include_intent_to_add: other.include_intent_to_add.or(self.include_intent_to_add),
+            backup: other.backup.or(self.backup),
+            backup_suffix: other.backup_suffix.or(self.backup_suffix),
+            max_file_size: other.max_file_size.or(self.max_file_size),
+            blank_at_eol: other.blank_at_eol.or(self.blank_at_eol),
+            unicode_whitespace: other.unicode_whitespace.or(self.unicode_whitespace),
+            whitespace_chars: other.whitespace_chars.or(self.whitespace_chars),
+            control_whitespace: other.control_whitespace.or(self.control_whitespace),
+            keep_blank_line_indent: other.keep_blank_line_indent.or(self.keep_blank_line_indent),
+            trim_eof_blank_lines: other.trim_eof_blank_lines.or(self.trim_eof_blank_lines),
+            squeeze_blank_lines: other.squeeze_blank_lines.or(self.squeeze_blank_lines),
+            ensure_final_newline: other.ensure_final_newline.or(self.ensure_final_newline),
+            markdown_breaks: other.markdown_breaks.or(self.markdown_breaks),
+            markdown_ext: other.markdown_ext.or(self.markdown_ext),
+            protect_regions: other.protect_regions.or(self.protect_regions),
+            space_before_tab: other.space_before_tab.or(self.space_before_tab),
+            tab_width: other.tab_width.or(self.tab_width),
+            tab_in_indent: other.tab_in_indent.or(self.tab_in_indent),
+            fix_tabs: other.fix_tabs.or(self.fix_tabs),
+            eol: other.eol.or(self.eol),
+            mixed_line_endings: other.mixed_line_endings.or(self.mixed_line_endings),
+            detect_invisible: other.detect_invisible.or(self.detect_invisible),
+            invisible_chars: other.invisible_chars.or(self.invisible_chars),
+            fix_invisible: other.fix_invisible.or(self.fix_invisible),
+            editorconfig: other.editorconfig.or(self.editorconfig),
+            utf16: other.utf16.or(self.utf16),
+            indent_with_non_tab: other.indent_with_non_tab.or(self.indent_with_non_tab),
+            fix_spaces: other.fix_spaces.or(self.fix_spaces),
+            regions: other.regions.or(self.regions),
+            rules: other.rules.or(self.rules),
+        }
+    }
+
+    /// Writes the effective values back into the parsed command line.
+    pub fn apply(&self, cli: &mut Cli) -> Result<(), RTrimError> {
+        cli.check = self.check.unwrap_or(false);
+        cli.no_add = !self.add.unwrap_or(true);
+        cli.whole_file = self.whole_file.unwrap_or(false);
+        cli.force_dirty = self.force_dirty.unwrap_or(false);
+        cli.include_intent_to_add = self.include_intent_to_add.unwrap_or(false);
+        cli.backup = self.backup.unwrap_or(false);
+        cli.backup_suffix = self.backup_suffix.clone().unwrap_or_else(|| String::from(DEFAULT_BACKUP_SUFFIX));
+
+        let max_file_size = self.max_file_size.as_deref().unwrap_or(DEFAULT_MAX_FILE_SIZE);
+        cli.max_file_size = Some(
+            parse_size(max_file_size).map_err(|e| RTrimError::Config(format!("max-file-size: {}", e)))?,
+        );
+
+        if let Some(exclude) = &self.exclude {
+            cli.file_filters.exclude = exclude
+                .iter()
+                .map(|p| Pattern::new(p).map_err(|e| RTrimError::Config(format!("exclude `{}`: {}", p, e))))
+                .collect::<Result<_, _>>()?;
+        }
+
+        cli.blank_at_eol = self.blank_at_eol.unwrap_or(true);
+        cli.trim_eof_blank_lines = self.trim_eof_blank_lines.unwrap_or(false);
+        if self.squeeze_blank_lines == Some(0) {
+            return Err(RTrimError::Config(String::from("squeeze-blank-lines: must be at least 1")));
+        }
+        cli.squeeze_blank_lines = self.squeeze_blank_lines;
+        cli.ensure_final_newline = self.ensure_final_newline.unwrap_or(false);
+        cli.markdown_breaks = self.markdown_breaks.unwrap_or(false);
+        cli.markdown_ext = self.markdown_ext.clone().unwrap_or_default();
+        cli.protect_regions = self.protect_regions.unwrap_or(false);
+        cli.regions = self
+            .regions
+            .iter()
+            .flatten()
+            .map(|(extension, region)| (extension.trim_start_matches('.').to_lowercase(), *region))
+            .collect();
+        cli.file_rules = self
+            .rules
+            .iter()
+            .flatten()
+            .map(|(key, rules)| RulesEntry::new(key, rules).map_err(|e| RTrimError::Config(format!("rules: {}", e))))
+            .collect::<Result<_, _>>()?;
+        cli.space_before_tab = self.space_before_tab.unwrap_or(false);
+        if self.tab_width == Some(0) {
+            return Err(RTrimError::Config(String::from("tab-width: must be at least 1")));
+        }
+        cli.tab_width = self.tab_width;
+        if self.fix_tabs == Some(0) {
+            return Err(RTrimError::Config(String::from("fix-tabs: must be at least 1")));
+        }
+        cli.tab_in_indent = self.tab_in_indent.unwrap_or(false);
+        cli.fix_tabs = self.fix_tabs;
+        cli.eol = self
+            .eol
+            .as_deref()
+            .map(|eol| Eol::from_str(eol, true).map_err(|_| RTrimError::Config(format!("eol: invalid value `{}`", eol))))
+            .transpose()?;
+        cli.mixed_line_endings = self.mixed_line_endings.unwrap_or(false);
+        cli.detect_invisible = self.detect_invisible.unwrap_or(false);
+        cli.invisible_chars = self
+            .invisible_chars
+            .iter()
+            .flatten()
+            .map(|c| parse_whitespace_char(c).map_err(|e| RTrimError::Config(format!("invisible-chars: {}", e))))
+            .collect::<Result<_, _>>()?;
+        cli.fix_invisible = self.fix_invisible.unwrap_or(false);
+        cli.editorconfig = self.editorconfig.unwrap_or(false);
+        cli.utf16 = self.utf16.unwrap_or(false);
+        cli.indent_with_non_tab = self.indent_with_non_tab.unwrap_or(false);
+        cli.fix_spaces = self.fix_spaces.unwrap_or(false);
+        // like git, the indentation can't be required to be both
+        if (cli.tab_in_indent || cli.fix_tabs.is_some()) && (cli.indent_with_non_tab || cli.fix_spaces) {
+            return Err(RTrimError::Config(String::from("tab-in-indent and indent-with-non-tab can't both be enabled")));
+        }
+        cli.unicode_whitespace = self.unicode_whitespace.unwrap_or(false);
+        cli.whitespace_chars = self
+            .whitespace_chars
+            .iter()
+            .flatten()
+            .map(|c| parse_whitespace_char(c).map_err(|e| RTrimError::Config(format!("whitespace-chars: {}", e))))
+            .collect::<Result<_, _>>()?;
+        cli.control_whitespace = self.control_whitespace.unwrap_or(false);
+        cli.keep_blank_line_indent = self.keep_blank_line_indent.unwrap_or(false);
+
+        cli.file_filters.ext = self.ext.clone().unwrap_or_default();
+        cli.file_filters.skip_ext = self.skip_ext.clone().unwrap_or_default();
+        cli.file_filters.no_default_excludes = !self.default_excludes.unwrap_or(true);
+
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Parses a size in bytes with an optional `K`, `M` or `G` suffix (also `KB`, `KiB`, ...), multiples of 1024.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let digits_end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits_end);
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => return Err(format!("invalid size `{}`", value)),
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size `{}`", value))
+}
+
+/// Applies git's `core.whitespace` syntax like `-blank-at-eof,trailing-space` to `config`,
+/// returns the unknown rules. The git rules rtrim doesn't check are accepted and ignored.
+pub fn parse_whitespace_rules(value: &str, config: &mut Config) -> Vec<String> {
+    let mut unknown: Vec<String> = Vec::new();
+
+    for rule in value.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+        let (enabled, name) = match rule.strip_prefix('-') {
+            Some(name) => (false, name),
+            None => (true, rule),
+        };
+
+        match name {
+            "blank-at-eol" => config.blank_at_eol = Some(enabled),
+            "blank-at-eof" => config.trim_eof_blank_lines = Some(enabled),
+            "trailing-space" => {
+                config.blank_at_eol = Some(enabled);
+                config.trim_eof_blank_lines = Some(enabled);
+            }
+            "space-before-tab" => config.space_before_tab = Some(enabled),
+            "tab-in-indent" => config.tab_in_indent = Some(enabled),
+            "indent-with-non-tab" => config.indent_with_non_tab = Some(enabled),
+            "cr-at-eol" => {}
+            name if name.starts_with("tabwidth=") => match name["tabwidth=".len()..].parse::<u32>() {
+                // git's limits
+                Ok(width @ 1..=63) if enabled => config.tab_width = Some(width),
+                _ => unknown.push(rule.to_string()),
+            },
+            _ => unknown.push(rule.to_string()),
+        }
+    }
+
+    unknown
+}
+
+/// Validates `--whitespace`, unknown rules are an error on the command line.
+pub fn parse_whitespace_arg(value: &str) -> Result<String, String> {
+    match parse_whitespace_rules(value, &mut Config::default()).as_slice() {
+        [] => Ok(value.to_string()),
+        unknown => Err(format!("unknown whitespace rule `{}`", unknown.join("`, `"))),
+    }
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value.split(',').map(|v| v.trim().to_string())
+}
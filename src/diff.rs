@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use crate::color::{self, BOLD, CYAN, GREEN, RED, RED_BACKGROUND};
+use crate::output::display_path;
+use crate::trim::TrimmedFile;
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT_LINES: u32 = 2;
+
+const NO_NEWLINE: &[u8] = b"\\ No newline at end of file\n";
+
+/// Renders the changes of `file` as a unified diff that can be piped into `git apply`.
+/// The lines are taken as they are, with their own line endings and encoding, only UTF-16 is shown as UTF-8.
+/// With `color` the removed trailing whitespaces are highlighted.
+pub fn unified_diff(path: &Path, file: &TrimmedFile, color: bool) -> Vec<u8> {
+    let last_line = file.line_count() as u32;
+    // a line whose line ending is converted changes as well
+    let changed = |line_no: u32| file.is_trimmed(line_no) || file.converts_line_ending(line_no);
+    let changes: Vec<u32> = (1..=last_line).filter(|l| changed(*l)).collect();
+
+    if changes.is_empty() {
+        return Vec::new();
+    }
+
+    // quoted as a whole like git does, `"a/caf\351.txt"`
+    let (old, new) = (display_path(&Path::new("a").join(path)), display_path(&Path::new("b").join(path)));
+    let mut diff = color::paint(&format!("--- {}\n+++ {}", old, new), BOLD, color).into_bytes();
+    diff.push(b'\n');
+    // the BOM belongs in front of the first line so that the diff applies
+    let bom = |line_no: u32| if line_no == 1 { file.bom() } else { "" };
+    // a last line without newline is followed by the marker, unless a newline is added
+    let push_line = |diff: &mut Vec<u8>, line: Vec<u8>, ending: &str| {
+        diff.extend_from_slice(&line);
+        match ending {
+            "" => {
+                diff.push(b'\n');
+                diff.extend_from_slice(NO_NEWLINE);
+            }
+            ending => diff.extend_from_slice(ending.as_bytes()),
+        }
+    };
+
+    // group changes whose context overlaps into the same hunk
+    let mut hunks: Vec<(u32, u32)> = Vec::new();
+    for line_no in changes {
+        let start = line_no.saturating_sub(CONTEXT_LINES).max(1);
+        let end = (line_no + CONTEXT_LINES).min(last_line);
+
+        match hunks.last_mut() {
+            Some((_, hunk_end)) if start <= *hunk_end + 1 => *hunk_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let count = end - start + 1;
+        let removed = (start..=end).filter(|l| file.is_removed(*l)).count() as u32;
+        // the lines removed before the hunk shift its start on the new side
+        let shift = (1..start).filter(|l| file.is_removed(*l)).count() as u32;
+        // like git, an empty new side starts at line 0
+        let new_start = if count == removed { start - 1 - shift } else { start - shift };
+        let header = format!("@@ -{},{} +{},{} @@", start, count, new_start, count - removed);
+        diff.extend_from_slice(color::paint(&header, CYAN, color).as_bytes());
+        diff.push(b'\n');
+
+        let mut line_no = start;
+        while line_no <= end {
+            if !changed(line_no) {
+                let line = [b" ", bom(line_no).as_bytes(), file.original_line(line_no)].concat();
+                push_line(&mut diff, line, file.original_ending(line_no));
+                line_no += 1;
+                continue;
+            }
+
+            // like git, print a run of changed lines as removals followed by additions
+            let run_start = line_no;
+            while line_no <= end && changed(line_no) {
+                line_no += 1;
+            }
+
+            for l in run_start..line_no {
+                let line = file.original_line(l);
+                // only removed trailing whitespaces are highlighted, a fixed indentation changes the whole line
+                let kept = Some(file.new_line(l)).filter(|new_line| line.starts_with(new_line)).unwrap_or(line);
+                let mut content = color::paint_bytes(&[b"-", bom(l).as_bytes(), kept].concat(), RED, color);
+                content.extend(color::paint_bytes(&line[kept.len()..], RED_BACKGROUND, color));
+                push_line(&mut diff, content, file.original_ending(l));
+            }
+            // the blank lines removed at the end of the file are not added back
+            for l in (run_start..line_no).filter(|l| !file.is_removed(*l)) {
+                let line = color::paint_bytes(&[b"+", bom(l).as_bytes(), file.new_line(l)].concat(), GREEN, color);
+                push_line(&mut diff, line, file.new_ending(l));
+            }
+        }
+    }
+
+    diff
+}
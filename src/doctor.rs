@@ -0,0 +1,168 @@
+use git2::Repository;
+use std::fmt::Display;
+
+use crate::cli::Cli;
+use crate::color::{self, GREEN, RED, YELLOW};
+use crate::error::RTrimError;
+use crate::hook::{installed_hook, shell_quote};
+use crate::output::Output;
+use crate::report::plural;
+use crate::{effective_config, Outcome};
+
+#[derive(PartialEq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// The result of one diagnostic.
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    hint: Option<&'static str>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Display) -> Self {
+        Check { name, status: Status::Pass, detail: detail.to_string(), hint: None }
+    }
+
+    fn warn(name: &'static str, detail: impl Display, hint: &'static str) -> Self {
+        Check { name, status: Status::Warn, detail: detail.to_string(), hint: Some(hint) }
+    }
+
+    fn fail(name: &'static str, detail: impl Display, hint: &'static str) -> Self {
+        Check { name, status: Status::Fail, detail: detail.to_string(), hint: Some(hint) }
+    }
+
+    fn print(&self, color: bool, out: &Output) {
+        let label = match self.status {
+            Status::Pass => color::paint("pass", GREEN, color),
+            Status::Warn => color::paint("warn", YELLOW, color),
+            Status::Fail => color::paint("fail", RED, color),
+        };
+
+        out.report(format_args!("[{}] {}: {}", label, self.name, self.detail));
+        if let Some(hint) = self.hint {
+            out.report(format_args!("       hint: {}", hint));
+        }
+    }
+}
+
+fn check_head(repo: &Repository) -> Check {
+    match repo.head().and_then(|head| Ok((head.peel_to_commit()?, head.shorthand().map(String::from)))) {
+        Ok((commit, branch)) => Check::pass(
+            "HEAD",
+            format!(
+                "{} on {}",
+                commit.as_object().short_id().ok().and_then(|id| id.as_str().map(String::from)).unwrap_or_default(),
+                branch.unwrap_or_else(|| String::from("a detached HEAD"))
+            ),
+        ),
+        Err(e) => Check::warn(
+            "HEAD",
+            format!("no commit yet ({})", e.message()),
+            "every staged line counts as added until the first commit",
+        ),
+    }
+}
+
+fn check_hook(repo: &Repository) -> Check {
+    let (hooks_dir, hook_path, command) = match installed_hook(repo) {
+        Ok(hook) => hook,
+        Err(e) => return Check::fail("pre-commit hook", e, "check core.hooksPath and the permissions of the hooks directory"),
+    };
+    let via = if hooks_dir.from_hooks_path { " (via core.hooksPath)" } else { "" };
+
+    let Some(command) = command else {
+        return Check::warn(
+            "pre-commit hook",
+            format!("not installed in {}{}", hook_path.display(), via),
+            "run `rtrim hook install`",
+        );
+    };
+
+    let this_binary = std::env::current_exe().map(|exe| shell_quote(&exe.to_string_lossy()));
+    if this_binary.as_deref().is_ok_and(|exe| exe == command) {
+        Check::pass("pre-commit hook", format!("installed in {}{}", hook_path.display(), via))
+    } else {
+        Check::warn(
+            "pre-commit hook",
+            format!("{}{} runs {}, not this binary", hook_path.display(), via, command),
+            "run `rtrim hook install` again to point it at this binary",
+        )
+    }
+}
+
+fn check_config(cli: &Cli, repo: &Repository, out: &Output) -> Check {
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+
+    match effective_config(cli, repo, workdir, out) {
+        Ok(config) => match config.to_toml().trim_end() {
+            "" => Check::pass("configuration", "defaults"),
+            toml => Check::pass("configuration", toml.replace('\n', ", ")),
+        },
+        Err(e) => Check::fail("configuration", e, "fix .rtrim.toml, the rtrim.* git config or the RTRIM_* variables"),
+    }
+}
+
+fn check_staged(repo: &Repository) -> Check {
+    let head_tree = repo.head().and_then(|head| head.peel_to_tree()).ok();
+
+    match repo.diff_tree_to_index(head_tree.as_ref(), None, None) {
+        Ok(diff) if diff.deltas().len() > 0 => Check::pass("staged changes", plural(diff.deltas().len(), "file")),
+        Ok(_) => Check::warn(
+            "staged changes",
+            "nothing staged",
+            "rtrim only looks at staged lines, stage changes with `git add` or use --all",
+        ),
+        Err(e) => Check::fail("staged changes", e, "the index could not be read"),
+    }
+}
+
+/// `doctor` subcommand: diagnoses why rtrim might not do anything. Fails if any check fails.
+pub fn run_doctor(cli: &Cli, out: &Output) -> Result<Outcome, RTrimError> {
+    let color = color::enabled(cli.color);
+    let mut checks: Vec<Check> = Vec::new();
+
+    match Repository::discover(std::env::current_dir()?) {
+        Ok(repo) => {
+            checks.push(match repo.workdir() {
+                Some(workdir) => Check::pass(
+                    "repository",
+                    format!(
+                        "{}, {} {}",
+                        repo.path().display(),
+                        if repo.is_worktree() { "linked worktree" } else { "working tree" },
+                        workdir.display()
+                    ),
+                ),
+                None => Check::warn(
+                    "repository",
+                    format!("{} is bare", repo.path().display()),
+                    "only check-commit and check-range work without a working tree",
+                ),
+            });
+            checks.push(check_head(&repo));
+            checks.push(check_hook(&repo));
+            checks.push(check_config(cli, &repo, out));
+            checks.push(check_staged(&repo));
+        }
+        Err(e) => checks.push(Check::fail("repository", e.message(), "run rtrim inside a git repository")),
+    }
+
+    let (major, minor, patch) = git2::Version::get().libgit2_version();
+    checks.push(Check::pass("libgit2", format!("{}.{}.{}", major, minor, patch)));
+
+    for check in &checks {
+        check.print(color, out);
+    }
+
+    if checks.iter().any(|check| check.status == Status::Fail) {
+        Err(RTrimError::Config(String::from("some checks failed")))
+    } else {
+        Ok(Outcome::Clean)
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const FILE_NAME: &str = ".editorconfig";
+
+/// The `.editorconfig` properties rtrim uses, `None` where no section sets them.
+#[derive(Default)]
+pub struct Properties {
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    /// `\n` or `\r\n`, rtrim can't write `cr`.
+    pub end_of_line: Option<&'static str>,
+}
+
+/// The sections of a `.editorconfig` file in the order they appear, and whether it is the root one.
+struct EditorConfigFile {
+    root: bool,
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+fn parse(content: &str) -> EditorConfigFile {
+    let mut file = EditorConfigFile { root: false, sections: Vec::new() };
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(glob) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            file.sections.push((glob.to_string(), Vec::new()));
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_lowercase(), value.trim().to_lowercase());
+
+        match file.sections.last_mut() {
+            Some((_, properties)) => properties.push((key, value)),
+            // the preamble only knows `root`
+            None if key == "root" => file.root = value == "true",
+            None => {}
+        }
+    }
+
+    file
+}
+
+/// The end of the `{}` or `[]` group opened at `start`, `None` if it isn't closed.
+fn closing(pattern: &[char], start: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => i += 1,
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// The alternatives of a `{s1,s2}` group, split at the commas outside nested groups.
+fn alternatives(group: &[char]) -> Vec<Vec<char>> {
+    let mut alternatives = vec![Vec::new()];
+    let mut depth = 0;
+    let mut chars = group.iter();
+
+    while let Some(&c) = chars.next() {
+        match c {
+            ',' if depth == 0 => {
+                alternatives.push(Vec::new());
+                continue;
+            }
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '\\' => {
+                let current = alternatives.last_mut().unwrap();
+                current.push(c);
+                current.extend(chars.next());
+                continue;
+            }
+            _ => {}
+        }
+        alternatives.last_mut().unwrap().push(c);
+    }
+
+    alternatives
+}
+
+/// The bounds of a `{num1..num2}` group.
+fn number_range(group: &[char]) -> Option<(i64, i64)> {
+    let group: String = group.iter().collect();
+    let (start, end) = group.split_once("..")?;
+
+    Some((start.parse().ok()?, end.parse().ok()?))
+}
+
+/// Whether `path` matches the EditorConfig glob `pattern`: `*` stays within a directory, `**` doesn't,
+/// `?`, `[name]`, `[!name]`, `{s1,s2}` and `{num1..num2}` work like in the specification.
+fn matches(pattern: &[char], path: &[char]) -> bool {
+    let Some(&c) = pattern.first() else {
+        return path.is_empty();
+    };
+
+    match c {
+        '*' if pattern.get(1) == Some(&'*') => (0..=path.len()).any(|i| matches(&pattern[2..], &path[i..])),
+        '*' => (0..=path.len())
+            .take_while(|i| *i == 0 || path[i - 1] != '/')
+            .any(|i| matches(&pattern[1..], &path[i..])),
+        '?' => path.first().is_some_and(|p| *p != '/') && matches(&pattern[1..], &path[1..]),
+        '[' => match closing(pattern, 0, '[', ']') {
+            Some(end) => {
+                let (negated, class) = match pattern[1..end].strip_prefix(&['!']) {
+                    Some(class) => (true, class),
+                    None => (false, &pattern[1..end]),
+                };
+                let Some(&p) = path.first().filter(|p| **p != '/') else {
+                    return false;
+                };
+                let mut in_class = false;
+                let mut i = 0;
+                while i < class.len() {
+                    if i + 2 < class.len() && class[i + 1] == '-' {
+                        in_class |= (class[i]..=class[i + 2]).contains(&p);
+                        i += 3;
+                    } else {
+                        in_class |= class[i] == p;
+                        i += 1;
+                    }
+                }
+                in_class != negated && matches(&pattern[end + 1..], &path[1..])
+            }
+            None => path.first() == Some(&'[') && matches(&pattern[1..], &path[1..]),
+        },
+        '{' => match closing(pattern, 0, '{', '}') {
+            Some(end) => {
+                let group = &pattern[1..end];
+                let rest = &pattern[end + 1..];
+
+                if let Some((low, high)) = number_range(group) {
+                    let digits =
+                        path.iter().enumerate().take_while(|(i, p)| p.is_ascii_digit() || (*i == 0 && **p == '-'));
+                    return (1..=digits.count()).any(|len| {
+                        let number: String = path[..len].iter().collect();
+                        number.parse::<i64>().is_ok_and(|n| n >= low && n <= high) && matches(rest, &path[len..])
+                    });
+                }
+
+                let alternatives = alternatives(group);
+                // a group without a comma is matched literally
+                if alternatives.len() == 1 {
+                    return path.first() == Some(&'{') && matches(&pattern[1..], &path[1..]);
+                }
+                alternatives.into_iter().any(|alternative| {
+                    let expanded: Vec<char> = alternative.into_iter().chain(rest.iter().copied()).collect();
+                    matches(&expanded, path)
+                })
+            }
+            None => path.first() == Some(&'{') && matches(&pattern[1..], &path[1..]),
+        },
+        '\\' if pattern.len() > 1 => path.first() == Some(&pattern[1]) && matches(&pattern[2..], &path[1..]),
+        c => path.first() == Some(&c) && matches(&pattern[1..], &path[1..]),
+    }
+}
+
+/// Whether the section `glob` of the `.editorconfig` in `dir` applies to `relative_path`, which is relative to `dir`.
+/// Like in .gitignore, a glob without a `/` matches the file name in any directory.
+fn section_matches(glob: &str, relative_path: &str) -> bool {
+    let path: Vec<char> = relative_path.chars().collect();
+
+    if glob.contains('/') {
+        let glob: Vec<char> = glob.trim_start_matches('/').chars().collect();
+        matches(&glob, &path)
+    } else {
+        let file_name: Vec<char> = relative_path.rsplit('/').next().unwrap_or_default().chars().collect();
+        matches(&glob.chars().collect::<Vec<char>>(), &file_name)
+    }
+}
+
+/// Resolves the properties of the file at `path` relative to `workdir`, from the `.editorconfig` files of its
+/// directory and every parent up to the one with `root = true`. Closer files and later sections win.
+pub fn properties(workdir: &Path, path: &Path) -> Properties {
+    let file_path = workdir.join(path);
+    let mut files: Vec<(&Path, EditorConfigFile)> = Vec::new();
+
+    for dir in file_path.ancestors().skip(1) {
+        let Ok(content) = fs::read_to_string(dir.join(FILE_NAME)) else {
+            continue;
+        };
+        let file = parse(&content);
+        let root = file.root;
+        files.push((dir, file));
+        if root {
+            break;
+        }
+    }
+
+    let mut values: BTreeMap<String, String> = BTreeMap::new();
+    for (dir, file) in files.iter().rev() {
+        let Ok(relative_path) = file_path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative_path = relative_path.to_string_lossy().replace('\\', "/");
+
+        for (glob, properties) in &file.sections {
+            if section_matches(glob, &relative_path) {
+                values.extend(properties.iter().cloned());
+            }
+        }
+    }
+    log::trace!("{}: .editorconfig {:?}", path.display(), values);
+
+    let flag = |key: &str| match values.get(key).map(String::as_str) {
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        // `unset` and invalid values fall back to the defaults
+        _ => None,
+    };
+
+    Properties {
+        trim_trailing_whitespace: flag("trim_trailing_whitespace"),
+        insert_final_newline: flag("insert_final_newline"),
+        end_of_line: match values.get("end_of_line").map(String::as_str) {
+            Some("lf") => Some("\n"),
+            Some("crlf") => Some("\r\n"),
+            _ => None,
+        },
+    }
+}
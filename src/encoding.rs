@@ -0,0 +1,56 @@
+/// The byte order of a UTF-16 file, told apart by its BOM.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Utf16 {
+    Le,
+    Be,
+}
+
+impl Utf16 {
+    /// The byte order of `content` if it starts with a UTF-16 BOM.
+    pub fn detect(content: &[u8]) -> Option<Self> {
+        match content {
+            [0xff, 0xfe, ..] => Some(Utf16::Le),
+            [0xfe, 0xff, ..] => Some(Utf16::Be),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Utf16::Le => "UTF-16LE",
+            Utf16::Be => "UTF-16BE",
+        }
+    }
+
+    /// Decodes `content` after its BOM to UTF-8, `None` for an odd length or unpaired surrogates.
+    pub fn decode(self, content: &[u8]) -> Option<Vec<u8>> {
+        let content = &content[2..];
+        if !content.len().is_multiple_of(2) {
+            return None;
+        }
+
+        let units: Vec<u16> = content
+            .chunks_exact(2)
+            .map(|unit| match self {
+                Utf16::Le => u16::from_le_bytes([unit[0], unit[1]]),
+                Utf16::Be => u16::from_be_bytes([unit[0], unit[1]]),
+            })
+            .collect();
+
+        String::from_utf16(&units).ok().map(String::into_bytes)
+    }
+
+    /// Encodes the UTF-8 `content` in this byte order, with the BOM in front.
+    pub fn encode(self, content: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(content.len() * 2 + 2);
+
+        for unit in std::iter::once(0xfeff).chain(String::from_utf8_lossy(content).encode_utf16()) {
+            match self {
+                Utf16::Le => encoded.extend(unit.to_le_bytes()),
+                Utf16::Be => encoded.extend(unit.to_be_bytes()),
+            }
+        }
+
+        encoded
+    }
+}
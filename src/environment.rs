@@ -0,0 +1,190 @@
+use std::env;
+
+use crate::cli::Cli;
+use crate::config::{parse_size, Config};
+use crate::error::RTrimError;
+use crate::output::Output;
+
+/// The variables read by `from_env`.
+const VARIABLES: &[&str] = &[
+    "RTRIM_CHECK",
+    "RTRIM_ADD",
+    "RTRIM_NO_ADD",
+    "RTRIM_EXCLUDE",
+    "RTRIM_EXT",
+    "RTRIM_SKIP_EXT",
+    "RTRIM_DEFAULT_EXCLUDES",
+    "RTRIM_NO_DEFAULT_EXCLUDES",
+    "RTRIM_WHOLE_FILE",
+    "RTRIM_FORCE_DIRTY",
+    "RTRIM_INCLUDE_INTENT_TO_ADD",
+    "RTRIM_BACKUP",
+    "RTRIM_BACKUP_SUFFIX",
+    "RTRIM_MAX_FILE_SIZE",
+    "RTRIM_BLANK_AT_EOL",
+    "RTRIM_UNICODE_WHITESPACE",
+    "RTRIM_WHITESPACE_CHARS",
+    "RTRIM_CONTROL_WHITESPACE",
+    "RTRIM_KEEP_BLANK_LINE_INDENT",
+    "RTRIM_TRIM_EOF_BLANK_LINES",
+    "RTRIM_SQUEEZE_BLANK_LINES",
+    "RTRIM_ENSURE_FINAL_NEWLINE",
+    "RTRIM_MARKDOWN_BREAKS",
+    "RTRIM_MARKDOWN_EXT",
+    "RTRIM_PROTECT_REGIONS",
+    "RTRIM_SPACE_BEFORE_TAB",
+    "RTRIM_TAB_WIDTH",
+    "RTRIM_TAB_IN_INDENT",
+    "RTRIM_FIX_TABS",
+    "RTRIM_INDENT_WITH_NON_TAB",
+    "RTRIM_FIX_SPACES",
+    "RTRIM_EOL",
+    "RTRIM_MIXED_LINE_ENDINGS",
+    "RTRIM_DETECT_INVISIBLE",
+    "RTRIM_INVISIBLE_CHARS",
+    "RTRIM_FIX_INVISIBLE",
+    "RTRIM_EDITORCONFIG",
+    "RTRIM_UTF16",
+];
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, RTrimError> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(RTrimError::Config(format!("{}: invalid boolean `{}`", name, value))),
+    }
+}
+
+fn split(value: &str, separator: char) -> Vec<String> {
+    value
+        .split(separator)
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Reads the `RTRIM_*` environment variables, `RTRIM_EXCLUDE` separates patterns with `:`,
+/// the extension lists with `,`. Empty variables are ignored.
+pub fn from_env(out: &Output) -> Result<Config, RTrimError> {
+    let mut config = Config::default();
+
+    for &name in VARIABLES {
+        let value = match env::var(name) {
+            Ok(value) if !value.is_empty() => value,
+            Ok(_) | Err(env::VarError::NotPresent) => continue,
+            Err(env::VarError::NotUnicode(_)) => {
+                return Err(RTrimError::Config(format!("{}: not valid unicode", name)))
+            }
+        };
+
+        match name {
+            "RTRIM_CHECK" => config.check = Some(parse_bool(name, &value)?),
+            "RTRIM_ADD" => config.add = Some(parse_bool(name, &value)?),
+            "RTRIM_NO_ADD" => config.add = Some(!parse_bool(name, &value)?),
+            "RTRIM_EXCLUDE" => config.exclude = Some(split(&value, ':')),
+            "RTRIM_EXT" => config.ext = Some(split(&value, ',')),
+            "RTRIM_SKIP_EXT" => config.skip_ext = Some(split(&value, ',')),
+            "RTRIM_DEFAULT_EXCLUDES" => config.default_excludes = Some(parse_bool(name, &value)?),
+            "RTRIM_NO_DEFAULT_EXCLUDES" => config.default_excludes = Some(!parse_bool(name, &value)?),
+            "RTRIM_WHOLE_FILE" => config.whole_file = Some(parse_bool(name, &value)?),
+            "RTRIM_FORCE_DIRTY" => config.force_dirty = Some(parse_bool(name, &value)?),
+            "RTRIM_INCLUDE_INTENT_TO_ADD" => config.include_intent_to_add = Some(parse_bool(name, &value)?),
+            "RTRIM_BACKUP" => config.backup = Some(parse_bool(name, &value)?),
+            "RTRIM_BACKUP_SUFFIX" => config.backup_suffix = Some(value.clone()),
+            "RTRIM_MAX_FILE_SIZE" => {
+                parse_size(&value).map_err(|e| RTrimError::Config(format!("{}: {}", name, e)))?;
+                config.max_file_size = Some(value.clone());
+            }
+            "RTRIM_BLANK_AT_EOL" => config.blank_at_eol = Some(parse_bool(name, &value)?),
+            "RTRIM_UNICODE_WHITESPACE" => config.unicode_whitespace = Some(parse_bool(name, &value)?),
+            "RTRIM_WHITESPACE_CHARS" => config.whitespace_chars = Some(split(&value, ',')),
+            "RTRIM_CONTROL_WHITESPACE" => config.control_whitespace = Some(parse_bool(name, &value)?),
+            "RTRIM_KEEP_BLANK_LINE_INDENT" => config.keep_blank_line_indent = Some(parse_bool(name, &value)?),
+            "RTRIM_TRIM_EOF_BLANK_LINES" => config.trim_eof_blank_lines = Some(parse_bool(name, &value)?),
+            "RTRIM_SQUEEZE_BLANK_LINES" => {
+                config.squeeze_blank_lines = Some(
+                    value
+                        .parse()
+                        .map_err(|_| RTrimError::Config(format!("{}: invalid number of lines `{}`", name, value)))?,
+                )
+            }
+            "RTRIM_ENSURE_FINAL_NEWLINE" => config.ensure_final_newline = Some(parse_bool(name, &value)?),
+            "RTRIM_MARKDOWN_BREAKS" => config.markdown_breaks = Some(parse_bool(name, &value)?),
+            "RTRIM_MARKDOWN_EXT" => config.markdown_ext = Some(split(&value, ',')),
+            "RTRIM_PROTECT_REGIONS" => config.protect_regions = Some(parse_bool(name, &value)?),
+            "RTRIM_SPACE_BEFORE_TAB" => config.space_before_tab = Some(parse_bool(name, &value)?),
+            "RTRIM_TAB_IN_INDENT" => config.tab_in_indent = Some(parse_bool(name, &value)?),
+            "RTRIM_FIX_TABS" => {
+                config.fix_tabs = Some(
+                    value
+                        .parse()
+                        .map_err(|_| RTrimError::Config(format!("{}: invalid tab width `{}`", name, value)))?,
+                )
+            }
+            "RTRIM_INDENT_WITH_NON_TAB" => config.indent_with_non_tab = Some(parse_bool(name, &value)?),
+            "RTRIM_FIX_SPACES" => config.fix_spaces = Some(parse_bool(name, &value)?),
+            "RTRIM_EOL" => config.eol = Some(value.clone()),
+            "RTRIM_MIXED_LINE_ENDINGS" => config.mixed_line_endings = Some(parse_bool(name, &value)?),
+            "RTRIM_DETECT_INVISIBLE" => config.detect_invisible = Some(parse_bool(name, &value)?),
+            "RTRIM_INVISIBLE_CHARS" => config.invisible_chars = Some(split(&value, ',')),
+            "RTRIM_FIX_INVISIBLE" => config.fix_invisible = Some(parse_bool(name, &value)?),
+            "RTRIM_EDITORCONFIG" => config.editorconfig = Some(parse_bool(name, &value)?),
+            "RTRIM_UTF16" => config.utf16 = Some(parse_bool(name, &value)?),
+            "RTRIM_TAB_WIDTH" => {
+                config.tab_width = Some(
+                    value
+                        .parse()
+                        .map_err(|_| RTrimError::Config(format!("{}: invalid tab width `{}`", name, value)))?,
+                )
+            }
+            _ => continue,
+        }
+
+        out.verbose(format_args!("{}={} from the environment", name, value));
+    }
+
+    Ok(config)
+}
+
+/// CI services and the variable that identifies them, `CI` is set by most of them.
+const CI_VARIABLES: &[(&str, &str)] = &[
+    ("GITHUB_ACTIONS", "GitHub Actions"),
+    ("GITLAB_CI", "GitLab CI"),
+    ("BUILDKITE", "Buildkite"),
+    ("CIRCLECI", "CircleCI"),
+    ("TRAVIS", "Travis CI"),
+    ("TF_BUILD", "Azure Pipelines"),
+    ("BITBUCKET_BUILD_NUMBER", "Bitbucket Pipelines"),
+    ("JENKINS_URL", "Jenkins"),
+    ("TEAMCITY_VERSION", "TeamCity"),
+    ("CI", "CI"),
+];
+
+/// The name of the CI service rtrim runs in, if any.
+pub fn detect_ci() -> Option<&'static str> {
+    CI_VARIABLES.iter().find_map(|(variable, name)| {
+        let value = env::var_os(variable)?;
+        let value = value.to_string_lossy().to_ascii_lowercase();
+
+        (!value.is_empty() && value != "false" && value != "0").then_some(*name)
+    })
+}
+
+/// Switches to check mode in CI unless `--fix` or `--no-ci-detect` is given, files rewritten in a
+/// pipeline are usually never committed.
+pub fn apply_ci_detection(cli: &mut Cli, out: &Output) {
+    if cli.no_ci_detect {
+        return;
+    }
+
+    if let Some(ci) = detect_ci() {
+        if cli.fix {
+            log::info!("running in {}, rewriting files because of --fix", ci);
+        } else if !cli.check && !cli.diff {
+            log::info!("running in {}, defaulting to check mode", ci);
+            out.info(format_args!("rtrim: running in {}, only checking, pass --fix to rewrite files", ci));
+            cli.check = true;
+        }
+    }
+}
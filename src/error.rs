@@ -1,8 +1,38 @@
 use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+
+use crate::report::plural;
 
 pub enum RTrimError {
     Git(git2::Error),
     Io(std::io::Error),
+    /// The report could not be written to the `--report-file` path.
+    ReportFile(PathBuf, std::io::Error),
+    /// Invalid configuration file or value.
+    Config(String),
+    /// The diff given to `--patch` can't be parsed.
+    Patch(String),
+    /// Some files could not be processed, their errors were already reported.
+    Failed(usize),
+    /// Some submodules could not be processed, their errors were already reported.
+    SubmodulesFailed(usize),
+    /// Some of the `--repo` repositories failed with their errors already reported, the highest exit code of them.
+    ReposFailed(usize, i32),
+    /// The repository at the path has no working tree to trim the files in.
+    BareRepository(PathBuf),
+}
+
+impl RTrimError {
+    /// Exit codes 0 and 1 are used for successful runs and 2 by clap for usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RTrimError::Config(_) | RTrimError::Patch(_) => 2,
+            RTrimError::Git(_) => 3,
+            RTrimError::Io(_) | RTrimError::ReportFile(..) | RTrimError::Failed(_) | RTrimError::SubmodulesFailed(_) => 4,
+            RTrimError::ReposFailed(_, exit_code) => *exit_code,
+            RTrimError::BareRepository(_) => 5,
+        }
+    }
 }
 
 impl From<git2::Error> for RTrimError {
@@ -20,8 +50,23 @@ impl From<std::io::Error> for RTrimError {
 impl Display for RTrimError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            RTrimError::Git(e) => e.fmt(f),
+            RTrimError::Git(e) => e.message().fmt(f),
             RTrimError::Io(e) => e.fmt(f),
+            RTrimError::ReportFile(path, e) => write!(f, "failed to write report to {}: {}", path.display(), e),
+            RTrimError::Config(message) => message.fmt(f),
+            RTrimError::Patch(message) => write!(f, "invalid patch: {}", message),
+            RTrimError::Failed(count) => write!(f, "{} could not be processed", plural(*count, "file")),
+            RTrimError::ReposFailed(count, _) => {
+                write!(f, "{} could not be processed", plural(*count, "repository"))
+            }
+            RTrimError::SubmodulesFailed(count) => {
+                write!(f, "{} could not be processed", plural(*count, "submodule"))
+            }
+            RTrimError::BareRepository(path) => write!(
+                f,
+                "rtrim needs a working tree, {} is a bare repository, use check-commit or --check instead",
+                path.display()
+            ),
         }
     }
 }
@@ -0,0 +1,135 @@
+use glob::{MatchOptions, Pattern};
+use std::path::Path;
+
+use crate::cli::FileFilters;
+use crate::editorconfig;
+use crate::output::{display_path, Output};
+use crate::report::{SkipReason, SkippedFile};
+use crate::scan::StagedLines;
+
+/// The extensions of patch files, their context lines can end with a significant space.
+const PATCH_EXTENSIONS: &[&str] = &["patch", "diff", "rej"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Returns the first pattern matching the repo relative `path`.
+/// Like in .gitignore, patterns without a `/` are matched against the file name only.
+pub fn find_match<'a>(patterns: &'a [Pattern], path: &str) -> Option<&'a Pattern> {
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|f| f.to_string_lossy())
+        .unwrap_or_default();
+
+    patterns.iter().find(|pattern| {
+        if pattern.as_str().contains('/') {
+            pattern.matches_with(path, MATCH_OPTIONS)
+        } else {
+            pattern.matches_with(&file_name, MATCH_OPTIONS)
+        }
+    })
+}
+
+/// Lowercase extension without the dot, `none` and the empty token stand for files without extension.
+fn normalize_extension(token: &str) -> String {
+    let token = token.trim_start_matches('.').to_lowercase();
+
+    if token == "none" {
+        String::new()
+    } else {
+        token
+    }
+}
+
+/// Lowercase extension of `path` without the dot, empty for files without extension.
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+pub fn has_extension(extensions: &[String], path: &str) -> bool {
+    let extension = extension(path);
+
+    extensions.iter().any(|e| normalize_extension(e) == extension)
+}
+
+fn extension_allowed(filters: &FileFilters, path: &str) -> bool {
+    (filters.ext.is_empty() || has_extension(&filters.ext, path)) && !has_extension(&filters.skip_ext, path)
+}
+
+/// Why `path` is filtered out by `--exclude`, `--ext`, `--skip-ext` or as patch file, together with a description
+/// for verbose output. Excludes win over the extension filters, listing a patch extension in `--ext` processes it.
+pub fn skip_reason(filters: &FileFilters, path: &str) -> Option<(SkipReason, String)> {
+    if let Some(pattern) = find_match(&filters.exclude, path) {
+        return Some((SkipReason::Excluded, format!("by exclude {}", pattern)));
+    }
+
+    if !extension_allowed(filters, path) {
+        return Some((SkipReason::Extension, String::from("by extension")));
+    }
+
+    if PATCH_EXTENSIONS.contains(&extension(path).as_str())
+        && !filters.no_default_excludes && !has_extension(&filters.ext, path) {
+        return Some((SkipReason::Patch, SkipReason::Patch.as_str().to_string()));
+    }
+
+    None
+}
+
+/// Removes the filtered out files and returns them as skipped.
+pub fn apply(files: &mut StagedLines, filters: &FileFilters, out: &Output) -> Vec<SkippedFile> {
+    let mut skipped = Vec::new();
+
+    files.retain(|file_name, findings| match skip_reason(filters, &file_name.to_string_lossy()) {
+        Some((reason, description)) => {
+            out.verbose(format_args!("{}: skipped {}", display_path(file_name), description));
+            skipped.push(SkippedFile::new(file_name, findings, reason));
+            false
+        }
+        None => true,
+    });
+
+    skipped
+}
+
+/// Removes the files `.editorconfig` sets `trim_trailing_whitespace = false` for, `workdir` is the root of their paths.
+pub fn skip_editorconfig(files: &mut StagedLines, workdir: &Path, out: &Output) -> Vec<SkippedFile> {
+    let mut skipped = Vec::new();
+
+    files.retain(|file_name, findings| {
+        if editorconfig::properties(workdir, file_name).trim_trailing_whitespace == Some(false) {
+            out.verbose(format_args!(
+                "{}: skipped by .editorconfig (trim_trailing_whitespace = false)",
+                display_path(file_name)
+            ));
+            skipped.push(SkippedFile::new(file_name, findings, SkipReason::EditorConfig));
+            false
+        } else {
+            true
+        }
+    });
+
+    skipped
+}
+
+/// Removes rtrim's own backups of earlier runs, so that they are never trimmed themselves.
+pub fn skip_backups(files: &mut StagedLines, suffix: &str, out: &Output) -> Vec<SkippedFile> {
+    let mut skipped = Vec::new();
+
+    files.retain(|file_name, findings| {
+        if file_name.to_string_lossy().ends_with(suffix) {
+            out.verbose(format_args!("{}: skipped as backup", display_path(file_name)));
+            skipped.push(SkippedFile::new(file_name, findings, SkipReason::Backup));
+            false
+        } else {
+            true
+        }
+    });
+
+    skipped
+}
@@ -0,0 +1,337 @@
+use git2::{AttrCheckFlags, Repository};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::diff;
+use crate::encoding::Utf16;
+use crate::index;
+use crate::output::{display_path, Output};
+use crate::report::plural;
+use crate::rules::Rules;
+use crate::scan::{working_tree_path, Finding, FindingKind, StagedLines};
+use crate::trim::{eol_name, TrimStats, TrimmedFile};
+
+fn calculate_hash<T: Hash>(t: &T) -> u64 {
+    let mut s = DefaultHasher::new();
+    t.hash(&mut s);
+    s.finish()
+}
+pub fn format_line_numbers(lines: &[u32]) -> String {
+    lines
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Whether `path` is a symlink itself, replacing it would either rewrite the target or turn the link into a
+/// regular file.
+pub fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+/// Writes the trimmed content next to `file_path` and renames it over the original.
+/// With a `backup_suffix` the original is copied to that path first, returns the path of the backup.
+pub fn replace_file(
+    file_path: &Path,
+    trimmed_file: &TrimmedFile,
+    backup_suffix: Option<&str>,
+) -> Result<Option<PathBuf>, io::Error> {
+    // the new file would have the default permissions, a script would lose its executable bit
+    let permissions = fs::metadata(file_path)?.permissions();
+
+    let backup_path = match backup_suffix {
+        Some(suffix) => {
+            let mut backup_name = file_path.as_os_str().to_owned();
+            backup_name.push(suffix);
+            let backup_path = PathBuf::from(backup_name);
+
+            // a stale backup is overwritten
+            fs::copy(file_path, &backup_path)?;
+            Some(backup_path)
+        }
+        None => None,
+    };
+
+    //setup file writer
+    let new_file_suffix = calculate_hash(&file_path).to_string();
+    let mut new_file_name = file_path.as_os_str().to_owned();
+    new_file_name.push(new_file_suffix);
+    let new_file_path = PathBuf::from(new_file_name);
+
+    let new_file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&new_file_path)?;
+    log::debug!("created temp file {}", new_file_path.display());
+
+    // the temp file name is the same on every run, a leftover one would make the next runs fail
+    let mut writer = BufWriter::new(new_file);
+    let replaced = trimmed_file
+        .write_to(&mut writer)
+        .and_then(|_| writer.flush())
+        .and_then(|_| fs::set_permissions(&new_file_path, permissions))
+        .and_then(|_| fs::rename(&new_file_path, file_path));
+    if let Err(e) = replaced {
+        _ = fs::remove_file(&new_file_path);
+        return Err(e);
+    }
+    log::debug!("renamed {} to {}", new_file_path.display(), file_path.display());
+
+    Ok(backup_path)
+}
+
+/// The lines among `findings` that are fixed by rewriting them, once each.
+pub fn flagged_lines(findings: &[Finding]) -> Vec<u32> {
+    let mut lines: Vec<u32> = findings.iter().filter(|f| f.kind.is_line_rule()).map(|f| f.line).collect();
+    lines.dedup();
+    lines
+}
+
+/// Removes the blank lines at the end of the file, converts the line endings
+/// and adds the final newline if `findings` flag them.
+pub fn fix_file_findings(trimmed_file: &mut TrimmedFile, findings: &[Finding], rules: &Rules) {
+    let extra_blank_lines: Vec<u32> =
+        findings.iter().filter(|f| f.kind == FindingKind::ExtraBlankLine).map(|f| f.line).collect();
+    trimmed_file.remove_lines(&extra_blank_lines);
+    if findings.iter().any(|f| f.kind == FindingKind::BlankAtEof) {
+        trimmed_file.remove_eof_blank_lines();
+    }
+    if let Some(eol) = rules.eol.filter(|_| findings.iter().any(|f| f.kind == FindingKind::LineEnding)) {
+        trimmed_file.convert_line_endings(eol);
+    }
+    if findings.iter().any(|f| f.kind == FindingKind::MissingFinalNewline) {
+        trimmed_file.add_final_newline();
+    }
+}
+
+/// Prints how many line endings of a file are converted.
+pub fn print_line_endings(file_name: &Path, trimmed_file: &TrimmedFile, out: &Output) {
+    let converted = trimmed_file.line_endings_converted();
+
+    if let Some(eol) = trimmed_file.eol().filter(|_| converted > 0) {
+        out.verbose(format_args!(
+            "{}: converted {} to {}",
+            display_path(file_name),
+            plural(converted, "line ending"),
+            eol_name(eol)
+        ));
+    }
+}
+
+/// The lines of `findings` that aren't in `trimmed_file` the way the scan saw them, the file changed since.
+/// Their fixes are undone, a line past the end of the file is returned too.
+pub fn changed_lines(trimmed_file: &mut TrimmedFile, findings: &[Finding]) -> Vec<u32> {
+    let line_count = trimmed_file.line_count() as u32;
+    let mut changed: Vec<u32> = findings
+        .iter()
+        .filter(|f| {
+            f.line > line_count
+                || (f.kind.is_line_rule() && String::from_utf8_lossy(trimmed_file.original_line(f.line)) != f.content)
+        })
+        .map(|f| f.line)
+        .collect();
+    changed.dedup();
+
+    for &line_no in &changed {
+        trimmed_file.keep_line(line_no);
+    }
+
+    changed
+}
+
+/// Trims the flagged lines of a file or every line with `whole_file`, the lines that changed since the scan are
+/// left alone and warned about. Returns whether every flagged line was trimmed.
+pub fn read_trimmed(
+    file_name: &Path,
+    file_path: &Path,
+    findings: &[Finding],
+    whole_file: bool,
+    rules: &Rules,
+    out: &Output,
+) -> Result<(TrimmedFile, bool), io::Error> {
+    if whole_file {
+        return Ok((TrimmedFile::read_all(file_path, rules)?, true));
+    }
+
+    let mut trimmed_file = TrimmedFile::read(file_path, flagged_lines(findings), rules)?;
+    let changed = changed_lines(&mut trimmed_file, findings);
+    for line_no in &changed {
+        match *line_no as usize > trimmed_file.line_count() {
+            true => out.warning(format_args!(
+                "{}:{}: skipped, the file has only {} now",
+                display_path(file_name),
+                line_no,
+                plural(trimmed_file.line_count(), "line")
+            )),
+            false => out.warning(format_args!(
+                "{}:{}: skipped, the line changed since the scan",
+                display_path(file_name),
+                line_no
+            )),
+        }
+    }
+
+    let findings: Vec<Finding> = findings.iter().filter(|f| !changed.contains(&f.line)).cloned().collect();
+    fix_file_findings(&mut trimmed_file, &findings, rules);
+
+    Ok((trimmed_file, changed.is_empty()))
+}
+
+/// Errors are reported per file and don't abort the run, returns the stats and the failed files.
+pub fn rtrim_files(
+    dir: &Path,
+    files: &StagedLines,
+    whole_file: bool,
+    rules: &Rules,
+    backup_suffix: Option<&str>,
+    out: &Output,
+) -> (TrimStats, Vec<PathBuf>) {
+    let mut stats = TrimStats::default();
+    let mut failed: Vec<PathBuf> = Vec::new();
+    let mut progress = out.progress("trimming", files.len());
+
+    for (file_name, findings) in files {
+        progress.tick();
+
+        let file_path = working_tree_path(dir, file_name);
+        if is_symlink(&file_path) {
+            out.warning(format_args!("{}: skipped, it is a symlink", display_path(file_name)));
+            continue;
+        }
+        let trimmed = read_trimmed(file_name, &file_path, findings, whole_file, &rules.for_file(dir, file_name), out)
+            .and_then(|(trimmed_file, verified)| {
+                replace_file(&file_path, &trimmed_file, backup_suffix).map(|backup| (trimmed_file, verified, backup))
+            });
+
+        let trimmed_file = match trimmed {
+            Ok((trimmed_file, verified, backup)) => {
+                if let Some(backup) = backup {
+                    out.verbose(format_args!("{}: backed up to {}", display_path(file_name), backup.display()));
+                }
+                // a file that changed since the scan isn't staged
+                if !verified {
+                    out.error(format_args!("{}: changed since the scan, not staged", display_path(file_name)));
+                    failed.push(file_name.clone());
+                }
+                trimmed_file
+            }
+            Err(e) => {
+                out.error(format_args!("{}: {}", display_path(file_name), e));
+                failed.push(file_name.clone());
+                continue;
+            }
+        };
+
+        let trimmed_lines = trimmed_file.trimmed_lines();
+        if !trimmed_lines.is_empty() {
+            out.verbose(format_args!(
+                "{}: trimmed {} ({})",
+                display_path(file_name),
+                plural(trimmed_lines.len(), "line"),
+                format_line_numbers(trimmed_lines)
+            ));
+        }
+        print_line_endings(file_name, &trimmed_file, out);
+
+        if trimmed_file.is_modified() {
+            stats.record(file_name, &trimmed_file);
+        }
+    }
+
+    (stats, failed)
+}
+
+pub fn print_diffs(
+    dir: &Path,
+    files: &StagedLines,
+    whole_file: bool,
+    rules: &Rules,
+    color: bool,
+    out: &Output,
+) -> Result<(), std::io::Error> {
+    for (file_name, findings) in files {
+        let file_path = working_tree_path(dir, file_name);
+        let (trimmed_file, _) =
+            read_trimmed(file_name, &file_path, findings, whole_file, &rules.for_file(dir, file_name), out)?;
+        let diff = diff::unified_diff(file_name, &trimmed_file, color);
+
+        if !diff.is_empty() {
+            out.report_bytes(&diff);
+        }
+    }
+
+    Ok(())
+}
+
+/// A UTF-16 working tree file with a `working-tree-encoding` attribute decoded to UTF-8, which git stores.
+fn working_tree_decoded(repo: &Repository, file: &Path) -> Option<Vec<u8>> {
+    let encoding = repo.get_attr(file, "working-tree-encoding", AttrCheckFlags::FILE_THEN_INDEX).ok().flatten()?;
+    if !encoding.to_ascii_uppercase().starts_with("UTF-16") {
+        return None;
+    }
+
+    let content = fs::read(working_tree_path(repo.workdir()?, file)).ok()?;
+    Utf16::detect(&content)?.decode(&content)
+}
+
+pub fn add_files<'a, T>(repo: &Repository, files: T) -> Result<(), git2::Error>
+where
+    T: Iterator<Item = &'a PathBuf>,
+{
+    let mut index = repo.index()?;
+
+    for path in files {
+        let file = path.display();
+        // untracked files stay untracked
+        let Some(staged) = index.get_path(path, 0) else {
+            log::info!("{} is not in the index, not staged", file);
+            continue;
+        };
+        // adding it would stage the whole file behind the user's back
+        if index::is_intent_to_add(&staged) {
+            log::info!("{} is intent-to-add, not staged", file);
+            continue;
+        }
+
+        // on disk in the other normalization form, adding that name would add a second entry
+        let on_disk = repo.workdir().map(|workdir| working_tree_path(workdir, path));
+        if let Some(on_disk) = on_disk.filter(|on_disk| !on_disk.ends_with(path)) {
+            let mut entry = staged;
+            entry.id = repo.blob_path(&on_disk)?;
+            entry.file_size = fs::metadata(&on_disk).map_or(entry.file_size, |metadata| metadata.len() as u32);
+            index.add(&entry)?;
+            log::info!("re-staged {} from {}", file, on_disk.display());
+            continue;
+        }
+
+        index.add_path(path)?;
+        let Some(mut entry) = index.get_path(path, 0) else {
+            continue;
+        };
+
+        // libgit2 doesn't know the working-tree-encoding, the stat data stays but the content is staged like git would
+        let decoded = working_tree_decoded(repo, path);
+        // only the content is re-staged, a mode change on disk that wasn't staged stays unstaged
+        if entry.mode == staged.mode && decoded.is_none() {
+            log::info!("re-staged {}", file);
+            continue;
+        }
+
+        entry.mode = staged.mode;
+        if let Some(content) = decoded {
+            entry.id = repo.blob(&content)?;
+            log::info!("re-staged {} decoded from its working-tree-encoding", file);
+        } else {
+            log::info!("re-staged {} with its staged mode {:o}", file, staged.mode);
+        }
+        index.add(&entry)?;
+    }
+
+    index.write()?;
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use git2::{Repository, StatusOptions};
+use std::path::Path;
+
+use crate::cli::Cli;
+use crate::error::RTrimError;
+use crate::output::Output;
+use crate::report::plural;
+use crate::rules::Rules;
+use crate::scan::{get_staged_lines_with_trailing_spaces, Base, Scan, ScanOptions};
+use crate::fix::{add_files, rtrim_files};
+use crate::{effective_config, filter, Outcome};
+
+/// Fails unless the index and the tracked files match HEAD, untracked files don't matter.
+pub fn ensure_clean(repo: &Repository, command: &str) -> Result<(), RTrimError> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false).include_ignored(false).exclude_submodules(true);
+
+    let changed: Vec<String> = repo
+        .statuses(Some(&mut options))?
+        .iter()
+        .filter(|entry| !entry.status().is_empty())
+        .filter_map(|entry| entry.path().map(String::from))
+        .collect();
+
+    if changed.is_empty() {
+        Ok(())
+    } else {
+        Err(RTrimError::Config(format!(
+            "{} needs a clean working tree and index, changed: {}",
+            command,
+            changed.join(", ")
+        )))
+    }
+}
+
+/// `fix-head` subcommand: trims the lines HEAD added and amends it with the same message, author and committer.
+/// Nothing is committed when there is nothing to trim, so `git rebase -x 'rtrim fix-head'` keeps clean commits.
+pub fn run_fix_head(cli: &Cli, out: &Output) -> Result<Outcome, RTrimError> {
+    let repo = Repository::discover(std::env::current_dir()?)?;
+    let Some(workdir) = repo.workdir().map(Path::to_path_buf) else {
+        return Err(RTrimError::Config(String::from("fix-head needs a repository with a working tree")));
+    };
+
+    let mut cli = cli.clone();
+    effective_config(&cli, &repo, &workdir, out)?.apply(&mut cli)?;
+
+    ensure_clean(&repo, "fix-head")?;
+
+    // the index matches HEAD, so diffing it against HEAD's parent yields the lines HEAD added
+    let rules = Rules::from_cli(&cli);
+    let options = ScanOptions {
+        base: Base::Amend,
+        path_filters: &[],
+        whole_file: false,
+        working_tree: false,
+        include_untracked: false,
+        include_intent_to_add: false,
+        max_file_size: cli.max_file_size.filter(|size| *size > 0),
+        force_text: &cli.force_text,
+        rules: &rules,
+    };
+    let Scan { mut files, .. } = get_staged_lines_with_trailing_spaces(&repo, &options, out)?;
+
+    filter::apply(&mut files, &cli.file_filters, out);
+    if let Some(suffix) = cli.backup_suffix() {
+        filter::skip_backups(&mut files, suffix, out);
+    }
+    if cli.editorconfig {
+        filter::skip_editorconfig(&mut files, &workdir, out);
+    }
+
+    let (stats, failed) = rtrim_files(&workdir, &files, false, &rules, cli.backup_suffix(), out);
+    if !failed.is_empty() {
+        return Err(RTrimError::Failed(failed.len()));
+    }
+
+    let head = repo.head()?.peel_to_commit()?;
+
+    if stats.files_modified() == 0 {
+        out.info("rtrim: no trailing whitespaces added by HEAD, nothing amended");
+        return Ok(Outcome::Clean);
+    }
+
+    add_files(&repo, stats.files.keys())?;
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let amended = head.amend(Some("HEAD"), None, None, None, None, Some(&tree))?;
+
+    out.info(format_args!(
+        "rtrim: amended {} as {}, {} trimmed in {}",
+        head.as_object().short_id()?.as_str().unwrap_or_default(),
+        repo.find_object(amended, None)?.short_id()?.as_str().unwrap_or_default(),
+        plural(stats.lines_trimmed(), "line"),
+        plural(stats.files_modified(), "file")
+    ));
+
+    // a non-zero exit code would stop the rebase
+    Ok(Outcome::Clean)
+}
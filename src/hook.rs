@@ -0,0 +1,231 @@
+use git2::{ErrorCode, Repository};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::cli::HookAction;
+use crate::error::RTrimError;
+use crate::output::Output;
+use crate::Outcome;
+
+const HOOK_NAME: &str = "pre-commit";
+const BEGIN_MARKER: &str = "# >>> rtrim >>>";
+const END_MARKER: &str = "# <<< rtrim <<<";
+/// Follows the rtrim command in the hook, exit code 1 means the staged files were trimmed and the commit goes on.
+const EXIT_CHECK: &str = "; rc=$?; [ \"$rc\" -le 1 ] || exit \"$rc\"";
+/// Followed the command in hooks installed by earlier versions, which aborted the commits rtrim had fixed.
+const OLD_EXIT_CHECK: &str = " || exit $?";
+
+/// The hooks directory and how it was determined.
+pub struct HooksDir {
+    pub path: PathBuf,
+    pub from_hooks_path: bool,
+}
+
+/// The git directory shared by all worktrees, linked worktrees point to it in their `commondir` file.
+fn common_dir(repo: &Repository) -> PathBuf {
+    match fs::read_to_string(repo.path().join("commondir")) {
+        // usually `../..`, resolved to keep the hook path readable
+        Ok(common_dir) => {
+            let common_dir = repo.path().join(common_dir.trim_end());
+            common_dir.canonicalize().unwrap_or(common_dir)
+        }
+        Err(_) => repo.path().to_path_buf(),
+    }
+}
+
+/// Honors `core.hooksPath`, relative paths are relative to the working tree like in git.
+pub fn hooks_dir(repo: &Repository) -> Result<HooksDir, RTrimError> {
+    match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) => {
+            let base = repo.workdir().unwrap_or_else(|| repo.path());
+
+            Ok(HooksDir {
+                path: base.join(path),
+                from_hooks_path: true,
+            })
+        }
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(HooksDir {
+            path: common_dir(repo).join("hooks"),
+            from_hooks_path: false,
+        }),
+        Err(e) => Err(RTrimError::Git(e)),
+    }
+}
+
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn hook_block(rtrim: &Path) -> String {
+    format!(
+        "{}\n{}{}\n{}\n",
+        BEGIN_MARKER,
+        shell_quote(&rtrim.to_string_lossy()),
+        EXIT_CHECK,
+        END_MARKER
+    )
+}
+
+/// The hook script without the lines added by `install`.
+fn remove_block(script: &str) -> String {
+    let mut result = String::new();
+    let mut inside = false;
+
+    for line in script.split_inclusive('\n') {
+        match line.trim_end() {
+            BEGIN_MARKER => inside = true,
+            END_MARKER => inside = false,
+            _ if !inside => result.push_str(line),
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// The rtrim command invoked by the installed hook, without the exit code check.
+fn installed_command(script: &str) -> Option<&str> {
+    let mut lines = script.lines().skip_while(|l| l.trim_end() != BEGIN_MARKER);
+    _ = lines.next();
+
+    lines.next().filter(|l| l.trim_end() != END_MARKER).map(|line| {
+        let line = line.trim_end();
+        line.strip_suffix(EXIT_CHECK).or_else(|| line.strip_suffix(OLD_EXIT_CHECK)).unwrap_or(line)
+    })
+}
+
+fn read_hook(path: &Path) -> Result<Option<String>, io::Error> {
+    match fs::read_to_string(path) {
+        Ok(script) => Ok(Some(script)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), io::Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), io::Error> {
+    Ok(())
+}
+
+/// Writes a new hook or, with `force`, appends to an existing hook that doesn't call rtrim yet.
+fn install(hook_path: &Path, force: bool, out: &Output) -> Result<(), RTrimError> {
+    let rtrim = std::env::current_exe()?;
+    let block = hook_block(&rtrim);
+
+    let script = match read_hook(hook_path)? {
+        None => format!("#!/bin/sh\n{}", block),
+        Some(script) if installed_command(&script).is_some() => {
+            // refresh the block, e.g. if the binary moved
+            let mut script = remove_block(&script);
+            script.push_str(&block);
+            script
+        }
+        Some(mut script) if force => {
+            if !script.is_empty() && !script.ends_with('\n') {
+                script.push('\n');
+            }
+            script.push_str(&block);
+            script
+        }
+        Some(_) => {
+            return Err(RTrimError::Config(format!(
+                "{} already exists and doesn't call rtrim, use --force to append to it",
+                hook_path.display()
+            )))
+        }
+    };
+
+    if let Some(dir) = hook_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(hook_path, script)?;
+    make_executable(hook_path)?;
+
+    out.info(format_args!("rtrim: installed {}", hook_path.display()));
+
+    Ok(())
+}
+
+/// Removes only the lines added by `install`, the hook file is deleted if nothing else is left.
+fn uninstall(hook_path: &Path, out: &Output) -> Result<(), RTrimError> {
+    let script = match read_hook(hook_path)? {
+        Some(script) if installed_command(&script).is_some() => script,
+        _ => {
+            out.info(format_args!("rtrim: not installed in {}", hook_path.display()));
+            return Ok(());
+        }
+    };
+
+    let remaining = remove_block(&script);
+
+    if remaining.lines().all(|l| l.trim().is_empty() || l.starts_with("#!")) {
+        fs::remove_file(hook_path)?;
+    } else {
+        fs::write(hook_path, remaining)?;
+    }
+
+    out.info(format_args!("rtrim: uninstalled from {}", hook_path.display()));
+
+    Ok(())
+}
+
+fn status(hooks_dir: &HooksDir, hook_path: &Path, out: &Output) -> Result<(), RTrimError> {
+    let via = if hooks_dir.from_hooks_path {
+        "core.hooksPath"
+    } else {
+        "the default hooks directory"
+    };
+
+    match read_hook(hook_path)?.as_deref().and_then(installed_command) {
+        Some(command) => out.report(format_args!(
+            "rtrim is installed in {} (via {}) and runs {}",
+            hook_path.display(),
+            via,
+            command
+        )),
+        None => out.report(format_args!(
+            "rtrim is not installed in {} (via {})",
+            hook_path.display(),
+            via
+        )),
+    }
+
+    Ok(())
+}
+
+/// The pre-commit hook path and the rtrim command it runs, if it is installed.
+pub fn installed_hook(repo: &Repository) -> Result<(HooksDir, PathBuf, Option<String>), RTrimError> {
+    let hooks_dir = hooks_dir(repo)?;
+    let hook_path = hooks_dir.path.join(HOOK_NAME);
+    let command = read_hook(&hook_path)?
+        .as_deref()
+        .and_then(installed_command)
+        .map(str::to_string);
+
+    Ok((hooks_dir, hook_path, command))
+}
+
+pub fn run_hook(action: &HookAction, out: &Output) -> Result<Outcome, RTrimError> {
+    let repo = Repository::discover(std::env::current_dir()?)?;
+    let hooks_dir = hooks_dir(&repo)?;
+    let hook_path = hooks_dir.path.join(HOOK_NAME);
+
+    match action {
+        HookAction::Install { force } => install(&hook_path, *force, out)?,
+        HookAction::Uninstall => uninstall(&hook_path, out)?,
+        HookAction::Status => status(&hooks_dir, &hook_path, out)?,
+    }
+
+    Ok(Outcome::Clean)
+}
@@ -0,0 +1,205 @@
+use git2::{DiffOptions, IndexEntry, IndexTime, Repository};
+use std::path::PathBuf;
+
+use crate::error::RTrimError;
+use crate::output::{display_path, Output};
+use crate::report::plural;
+use crate::rules::Rules;
+use crate::scan::{Finding, FindingKind, StagedLines};
+use crate::fix::{fix_file_findings, flagged_lines, format_line_numbers, print_line_endings};
+use crate::trim::{TrimStats, TrimmedFile};
+
+/// `GIT_INDEX_ENTRY_SKIP_WORKTREE` of the extended flags and `GIT_INDEX_ENTRY_VALID` of the flags.
+const SKIP_WORKTREE: u16 = 1 << 14;
+const ASSUME_VALID: u16 = 1 << 15;
+/// `GIT_INDEX_ENTRY_INTENT_TO_ADD` of the extended flags, set by `git add -N`.
+const INTENT_TO_ADD: u16 = 1 << 13;
+
+/// Whether the entry was added with `git add -N`, its blob is empty until the file is really added.
+pub fn is_intent_to_add(entry: &IndexEntry) -> bool {
+    entry.flags_extended & INTENT_TO_ADD != 0
+}
+
+/// The files among `files` that are intent-to-add.
+pub fn intent_to_add(repo: &Repository, files: &StagedLines) -> Result<StagedLines, RTrimError> {
+    let index = repo.index()?;
+
+    Ok(files
+        .iter()
+        .filter(|(file_name, _)| index.get_path(file_name, 0).is_some_and(|entry| is_intent_to_add(&entry)))
+        .map(|(file_name, findings)| (file_name.clone(), findings.clone()))
+        .collect())
+}
+
+/// The files among `files` whose working tree content differs from the staged one, e.g. after `git add -p`.
+pub fn partially_staged(repo: &Repository, files: &StagedLines) -> StagedLines {
+    files
+        .iter()
+        .filter(|(file_name, _)| repo.status_file(file_name).is_ok_and(|status| status.is_wt_modified()))
+        .map(|(file_name, findings)| (file_name.clone(), findings.clone()))
+        .collect()
+}
+
+/// The files among `files` whose index entry has the skip-worktree or assume-unchanged bit set, with its name.
+/// Their working tree files are meant to differ from the staged content, e.g. local configuration overrides.
+pub fn worktree_ignored(repo: &Repository, files: &StagedLines) -> Result<Vec<(PathBuf, &'static str)>, RTrimError> {
+    let index = repo.index()?;
+
+    Ok(files
+        .keys()
+        .filter_map(|file_name| {
+            let entry = index.get_path(file_name, 0)?;
+            let bit = if entry.flags_extended & SKIP_WORKTREE != 0 {
+                "skip-worktree"
+            } else if entry.flags & ASSUME_VALID != 0 {
+                "assume-unchanged"
+            } else {
+                return None;
+            };
+            Some((file_name.clone(), bit))
+        })
+        .collect())
+}
+
+/// The findings of partially staged files moved from the staged lines to the lines of the working tree files.
+/// The findings of lines that were changed since they were staged are dropped, their unstaged changes stay as they are.
+pub fn working_tree_findings(repo: &Repository, files: &StagedLines) -> Result<StagedLines, RTrimError> {
+    let index = repo.index()?;
+    let mut result = StagedLines::new();
+
+    for (file_name, findings) in files {
+        let mut options = DiffOptions::new();
+        options.pathspec(file_name).disable_pathspec_match(true).context_lines(0);
+
+        // the staged and working tree line ranges of the unstaged changes
+        let mut hunks: Vec<(u32, u32, u32, u32)> = Vec::new();
+        repo.diff_index_to_workdir(Some(&index), Some(&mut options))?.foreach(
+            &mut |_, _| true,
+            None,
+            Some(&mut |_, hunk| {
+                hunks.push((hunk.old_start(), hunk.old_lines(), hunk.new_start(), hunk.new_lines()));
+                true
+            }),
+            None,
+        )?;
+
+        // the last staged line a change touches, an insertion comes after its start line
+        let last_changed = |&(start, lines, _, _): &(u32, u32, u32, u32)| if lines == 0 { start } else { start + lines - 1 };
+        let working_tree_line = |line_no: u32| -> Option<u32> {
+            let mut shift: i64 = 0;
+            for hunk @ &(start, lines, _, new_lines) in &hunks {
+                if lines > 0 && (start..start + lines).contains(&line_no) {
+                    return None;
+                }
+                if line_no <= last_changed(hunk) {
+                    break;
+                }
+                shift += new_lines as i64 - lines as i64;
+            }
+            Some((line_no as i64 + shift) as u32)
+        };
+
+        let moved: Vec<Finding> = findings
+            .iter()
+            .filter_map(|finding| {
+                let line = match finding.kind {
+                    // the whole file is converted
+                    FindingKind::LineEnding => working_tree_line(finding.line).unwrap_or(finding.line),
+                    // the blank lines have to stay at the end of the file
+                    FindingKind::BlankAtEof if hunks.iter().any(|hunk| last_changed(hunk) >= finding.line) => {
+                        return None;
+                    }
+                    _ => working_tree_line(finding.line)?,
+                };
+                Some(Finding { line, ..finding.clone() })
+            })
+            .collect();
+        log::debug!(
+            "{}: {} of {} findings apply to the working tree",
+            file_name.display(),
+            moved.len(),
+            findings.len()
+        );
+
+        if !moved.is_empty() {
+            result.insert(file_name.clone(), moved);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Trims the flagged lines of the staged blobs and points the index entries at the trimmed blobs,
+/// the working tree is left untouched. Returns the stats and the files that could not be processed.
+pub fn trim_index_entries(
+    repo: &Repository,
+    files: &StagedLines,
+    rules: &Rules,
+    out: &Output,
+) -> Result<(TrimStats, Vec<PathBuf>), RTrimError> {
+    let mut index = repo.index()?;
+    let mut stats = TrimStats::default();
+    let mut failed: Vec<PathBuf> = Vec::new();
+    let mut progress = out.progress("trimming", files.len());
+
+    for (file_name, findings) in files {
+        progress.tick();
+
+        let Some(mut entry) = index.get_path(file_name, 0) else {
+            out.error(format_args!("{}: not in the index", display_path(file_name)));
+            failed.push(file_name.clone());
+            continue;
+        };
+
+        let blob = repo.find_blob(entry.id)?;
+        let rules = match repo.workdir() {
+            Some(workdir) => rules.for_file(workdir, file_name),
+            None => rules.for_path(file_name),
+        };
+        let mut trimmed_file = match TrimmedFile::parse(blob.content(), flagged_lines(findings), &rules) {
+            Ok(trimmed_file) => trimmed_file,
+            Err(e) => {
+                out.error(format_args!("{}: {}", display_path(file_name), e));
+                failed.push(file_name.clone());
+                continue;
+            }
+        };
+
+        fix_file_findings(&mut trimmed_file, findings, &rules);
+
+        if !trimmed_file.is_modified() {
+            continue;
+        }
+
+        let mut content: Vec<u8> = Vec::new();
+        trimmed_file.write_to(&mut content)?;
+
+        // the mode is kept, the stat data is cleared so git hashes the working tree file again instead of taking
+        // the untrimmed file for unchanged
+        entry.id = repo.blob(&content)?;
+        entry.file_size = content.len() as u32;
+        entry.ctime = IndexTime::new(0, 0);
+        entry.mtime = IndexTime::new(0, 0);
+        entry.dev = 0;
+        entry.ino = 0;
+        index.add(&entry)?;
+        log::info!("rewrote the index entry of {}", file_name.display());
+
+        let trimmed_lines = trimmed_file.trimmed_lines();
+        if !trimmed_lines.is_empty() {
+            out.verbose(format_args!(
+                "{}: trimmed {} in the index ({})",
+                display_path(file_name),
+                plural(trimmed_lines.len(), "line"),
+                format_line_numbers(trimmed_lines)
+            ));
+        }
+        print_line_endings(file_name, &trimmed_file, out);
+
+        stats.record(file_name, &trimmed_file);
+    }
+
+    index.write()?;
+
+    Ok((stats, failed))
+}
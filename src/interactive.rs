@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use crate::output::{display_path, Output};
+use crate::report::plural;
+use crate::scan::{Finding, StagedLines};
+
+enum Answer {
+    Yes,
+    No,
+    All,
+    Quit,
+    List,
+}
+
+fn ask(out: &Output, file_name: &Path, line_count: usize) -> Result<Answer, io::Error> {
+    let stdin = io::stdin();
+
+    loop {
+        out.prompt(format_args!(
+            "{}: {} with trailing whitespaces, trim? [y,n,a,q,l,?] ",
+            display_path(file_name),
+            plural(line_count, "line")
+        ));
+
+        let mut input = String::new();
+        if stdin.lock().read_line(&mut input)? == 0 {
+            // EOF, treat it like quit
+            return Ok(Answer::Quit);
+        }
+
+        match input.trim() {
+            "y" => return Ok(Answer::Yes),
+            "n" => return Ok(Answer::No),
+            "a" => return Ok(Answer::All),
+            "q" => return Ok(Answer::Quit),
+            "l" => return Ok(Answer::List),
+            _ => out.prompt(
+                "y - trim this file\n\
+                 n - do not trim this file\n\
+                 a - trim this and all remaining files\n\
+                 q - quit, do not trim this or any of the remaining files\n\
+                 l - list the flagged lines\n",
+            ),
+        }
+    }
+}
+
+fn list_lines(out: &Output, findings: &[Finding]) {
+    for finding in findings {
+        out.prompt(format_args!("{:>6}: {:?}\n", finding.line, finding.content));
+    }
+}
+
+/// Asks per file whether it should be trimmed, returns the accepted files and the names of the declined ones.
+/// Falls back to accepting everything if stdin is not a terminal.
+pub fn select_files(
+    out: &Output,
+    files: StagedLines,
+) -> Result<(StagedLines, Vec<PathBuf>), io::Error> {
+    if !io::stdin().is_terminal() {
+        out.warning("stdin is not a terminal, --interactive is ignored");
+        return Ok((files, Vec::new()));
+    }
+
+    let mut accepted = BTreeMap::new();
+    let mut declined = Vec::new();
+    let mut accept_all = false;
+    let mut quit = false;
+
+    for (file_name, lines) in files {
+        let mut answer = if accept_all {
+            Answer::Yes
+        } else if quit {
+            Answer::No
+        } else {
+            ask(out, &file_name, lines.len())?
+        };
+
+        while let Answer::List = answer {
+            list_lines(out, &lines);
+            answer = ask(out, &file_name, lines.len())?;
+        }
+
+        let accept = match answer {
+            Answer::Yes => true,
+            Answer::No => false,
+            Answer::All => {
+                accept_all = true;
+                true
+            }
+            Answer::Quit => {
+                quit = true;
+                false
+            }
+            Answer::List => unreachable!(),
+        };
+
+        if accept {
+            accepted.insert(file_name, lines);
+        } else {
+            declined.push(file_name);
+        }
+    }
+
+    Ok((accepted, declined))
+}
@@ -0,0 +1,45 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::env;
+use std::str::FromStr;
+
+use crate::error::RTrimError;
+
+pub const LOG_ENV_VAR: &str = "RTRIM_LOG";
+
+/// Writes log records to stderr, so they never end up in machine-readable stdout.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// `--log-level` wins over `RTRIM_LOG`, logging is off by default.
+pub fn init(log_level: Option<LevelFilter>) -> Result<(), RTrimError> {
+    let level = match log_level {
+        Some(level) => level,
+        None => match env::var(LOG_ENV_VAR) {
+            Ok(value) => LevelFilter::from_str(&value)
+                .map_err(|_| RTrimError::Config(format!("{}: invalid log level `{}`", LOG_ENV_VAR, value)))?,
+            Err(_) => LevelFilter::Off,
+        },
+    };
+
+    log::set_max_level(level);
+
+    if level != LevelFilter::Off {
+        // only fails if a logger is already set
+        _ = log::set_logger(&StderrLogger);
+    }
+
+    Ok(())
+}
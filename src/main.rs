@@ -1,174 +1,285 @@
-use git2::{DiffOptions, Repository};
-use std::collections::hash_map::DefaultHasher;
-use std::fs::File;
-use std::hash::{Hash, Hasher};
+use clap::error::{ContextKind, ContextValue, ErrorKind};
+use clap::{CommandFactory, Parser};
+use git2::{Index, Repository};
 use std::{
-    collections::{HashMap, VecDeque},
-    io::{BufRead, BufReader, BufWriter, Write},
-    ops::Add,
+    collections::BTreeMap,
+    io::{self, BufWriter, Read, Write},
     path::{Path, PathBuf},
-    str,
 };
-use std::{env, fs::OpenOptions};
-
+use std::{env, fs, process};
+
+mod cli;
+mod color;
+mod commit_msg;
+mod config;
+mod diff;
+mod doctor;
+mod editorconfig;
+mod encoding;
+mod environment;
 mod error;
+mod filter;
+mod fix;
+mod fix_head;
+mod hook;
+mod index;
+mod interactive;
+mod logger;
+mod output;
+mod patch;
+mod pathspec;
+mod plain;
+mod range;
+mod regions;
+mod report;
+mod rewrite;
+mod rules;
+mod scan;
+mod trim;
+mod watch;
+use cli::{Cli, Command, FileFilters, Format};
+use config::Config;
 use error::RTrimError;
+use fix::{add_files, fix_file_findings, flagged_lines, print_diffs, rtrim_files};
+use output::{display_path, Output};
+use report::{Report, SkipReason, SkippedFile};
+use rules::Rules;
+use scan::{
+    get_staged_lines_with_trailing_spaces, get_tracked_lines_with_trailing_spaces, working_tree_path, Base, Scan,
+    ScanOptions, StagedLines,
+};
+use trim::{TrimStats, TrimmedFile};
 
+fn read_path_filters_from_stdin(null_delimited: bool) -> Result<Vec<String>, io::Error> {
+    let mut input = Vec::new();
+    io::stdin().lock().read_to_end(&mut input)?;
 
-#[cfg(windows)]
-const LINE_ENDING: &[u8] = b"\r\n";
-#[cfg(not(windows))]
-const LINE_ENDING: &[u8] = b"\n";
-
-
-fn path_combine<T>(path1: T, path2: T) -> PathBuf
-where
-    T: AsRef<Path>,
-    PathBuf: From<T>,
-    T: Into<PathBuf>,
-{
-    let mut buf = PathBuf::from(path1);
-    buf.push(path2);
-    buf
+    split_paths(&input, null_delimited)
 }
 
-fn calculate_hash<T: Hash>(t: &T) -> u64 {
-    let mut s = DefaultHasher::new();
-    t.hash(&mut s);
-    s.finish()
-}
-/*
-    ADR: git2 only returns LF, no CRLFs => no need to check for CRLF
-*/
-fn trailing_whitespaces(s: &str) -> bool {
-    s.ends_with(' ')
-    || s.ends_with('\t')
-    || s.ends_with(" \n")
-    || s.ends_with("\t\n")
+/// Splits newline or NUL delimited paths, empty entries are dropped.
+fn split_paths(input: &[u8], null_delimited: bool) -> Result<Vec<String>, io::Error> {
+    let delimiter = if null_delimited { b'\0' } else { b'\n' };
+
+    input
+        .split(|b| *b == delimiter)
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            String::from_utf8(p.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+        .collect()
 }
 
-fn get_staged_lines_with_trailing_spaces(
-    repo: &Repository,
-    path_filters: &[String]
-) -> Result<HashMap<String, VecDeque<u32>>, RTrimError> {
-    let mut result: HashMap<String, VecDeque<u32>> = HashMap::new();
-
-    //get head_tree
-    let head_tree = match repo.head() {
-        Ok(r) => Option::Some(r.peel_to_tree()?),
-        Err(_) => Option::None,
+
+/// Renders the report in the selected format, `None` if the format has nothing to show.
+fn render_report(cli: &Cli, report: &Report, color: bool) -> Result<Option<String>, io::Error> {
+    let preview = cli.check || cli.diff;
+
+    if cli.list_files {
+        return Ok(Some(report.to_file_list(preview, cli.null_delimited)));
+    }
+
+    let rendered = match cli.format {
+        _ if cli.diff => None,
+        Format::Text => (cli.check && !report.files.is_empty()).then(|| report.to_text(color, &Rules::from_cli(cli))),
+        Format::Json => Some(report.to_json().map_err(io::Error::from)?),
+        Format::Github => (!report.files.is_empty()).then(|| report.to_github(preview)),
+        Format::Sarif => Some(report.to_sarif(preview).map_err(io::Error::from)?),
+        Format::Checkstyle => Some(report.to_checkstyle(preview)),
+        Format::Rdjson => Some(report.to_rdjson(preview, cli.severity, &Rules::from_cli(cli)).map_err(io::Error::from)?),
     };
 
-    //get index
-    let index = Option::Some(repo.index()?);
+    Ok(rendered.map(|text| text + "\n"))
+}
+
+/// Writes the rendered report to `path`, creating its parent directories and truncating an existing file.
+fn write_report_file(path: &Path, rendered: Option<String>) -> Result<(), RTrimError> {
+    let write = || -> Result<(), io::Error> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, rendered.unwrap_or_default())
+    };
 
-    let mut diff_options = DiffOptions::new();
+    write().map_err(|e| RTrimError::ReportFile(path.to_path_buf(), e))
+}
 
-    for path_filter in path_filters {
-        diff_options.pathspec(path_filter);
-    }
-
-    //get diff
-    let diff_result = repo.diff_tree_to_index(head_tree.as_ref(), index.as_ref(), Some(&mut diff_options))?;
-
-    //iterate over the diff_result and put lines with trailing spaces in the result
-    diff_result.print(git2::DiffFormat::Patch, |d, _, diff_line| -> bool {
-        if let Some(line_no) = diff_line.new_lineno() {
-            let raw_line = diff_line.content();
-
-            if let Ok(line) = str::from_utf8(raw_line) {
-                if trailing_whitespaces(line) {
-                    let file_path = PathBuf::from(d.new_file().path().unwrap());
-                    let file_path_str = String::from(file_path.to_str().unwrap());
-
-                    match result.get_mut(&file_path_str) {
-                        Some(l) => {
-                            l.push_back(line_no);
-                        }
-                        None => {
-                            let mut queue: VecDeque<u32> = VecDeque::new();
-                            queue.push_back(line_no);
-                            result.insert(file_path_str, queue);
-                        }
-                    }
-                }
+/// Prints the report to stdout or the report file, diffs are printed separately.
+fn write_report(cli: &Cli, report: &Report, color: bool, out: &Output) -> Result<(), RTrimError> {
+    match &cli.report_file {
+        Some(path) => write_report_file(path, render_report(cli, report, false)?),
+        None => {
+            if let Some(rendered) = render_report(cli, report, color)? {
+                out.report_raw(rendered);
             }
+            Ok(())
         }
+    }
+}
 
-        true
-    })?;
+/// Prints the report followed by the summary, a file list is printed alone for scripts.
+fn print_report(cli: &Cli, report: &Report, color: bool, out: &Output) -> Result<(), RTrimError> {
+    write_report(cli, report, color, out)?;
+    if cli.list_files {
+        return Ok(());
+    }
+    print_stat(cli, report, out);
+    out.info(report.summary(cli.check || cli.diff));
 
-    Ok(result)
+    Ok(())
 }
 
-fn rtrim_files(dir: &Path, files: &HashMap<String, VecDeque<u32>>) -> Result<(), std::io::Error> {
-    for (file_name, l) in files {
-        let mut lines = l.clone();
-
-        //setup file reader
-        let file_path = path_combine(dir, file_name.as_ref());
-        let file = File::open(&file_path)?;
-        let reader = BufReader::new(file);
+/// The `--stat` table, sized for hook output.
+fn print_stat(cli: &Cli, report: &Report, out: &Output) {
+    if cli.stat && !report.files.is_empty() {
+        out.info(report.to_stat(cli.check || cli.diff, 80));
+    }
+}
 
-        //setup file writer
-        let new_file_suffix = calculate_hash(file_name).to_string();
-        let new_file_name = String::from(file_name).add(&new_file_suffix);
-        let new_file_path = path_combine(dir, new_file_name.as_ref());
+/// Trims stdin to stdout like a git clean filter. Changes never fail the run, git would abort the operation.
+fn run_filter(
+    file_filters: &FileFilters,
+    path: Option<&Path>,
+    rules: &Rules,
+    out: &Output,
+) -> Result<Outcome, RTrimError> {
+    let mut input = Vec::new();
+    io::stdin().lock().read_to_end(&mut input)?;
 
-        let new_file = OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&new_file_path)?;
+    let mut stdout = BufWriter::new(io::stdout().lock());
 
-        let mut writer = BufWriter::new(new_file);
+    let skip = path.and_then(|p| filter::skip_reason(file_filters, &p.to_string_lossy()));
 
-        let mut line_no = 1;
-        for value in reader.lines() {
-            //wrap result
-            let line: String = value?;
-            let mut line_to_write: &str = line.as_str();
+    match skip {
+        Some((_, description)) => {
+            out.verbose(format_args!("{}: skipped {}", path.unwrap().display(), description));
+            stdout.write_all(&input)?;
+        }
+        None => {
+            let rules = path.map_or_else(|| rules.clone(), |p| rules.for_path(p));
+            trim::trim_all_lines(&input, &rules, &mut stdout)?;
+        }
+    }
 
-            if let Some(l) = lines.front() {
-                if line_no == *l {
-                    line_to_write = line.trim_end();
+    stdout.flush()?;
 
-                    _ = lines.pop_front();
-                }
-            }
+    Ok(Outcome::Clean)
+}
 
-            writer.write_all(line_to_write.as_bytes())?;
-            writer.write_all(LINE_ENDING)?;
+/// The result of a run without errors, ordered from best to worst.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Outcome {
+    /// Nothing needed trimming.
+    Clean,
+    /// Files were trimmed.
+    Modified,
+    /// Check or diff mode found lines with trailing whitespaces, or files with findings were skipped for their
+    /// unstaged changes or because they are missing from the working tree.
+    Found,
+}
 
-            line_no += 1;
+impl Outcome {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Clean => 0,
+            Outcome::Modified | Outcome::Found => 1,
         }
+    }
+}
 
-        writer.flush()?;
+fn run(cli: &Cli, out: &Output) -> Result<Outcome, RTrimError> {
+    // stdin can only be read once for all repositories
+    let path_filters = if cli.paths_from_stdin {
+        read_path_filters_from_stdin(cli.null_delimited)?
+    } else if let Some(file_list) = &cli.file_list {
+        let input = fs::read(file_list)
+            .map_err(|e| RTrimError::Config(format!("failed to read {}: {}", file_list.display(), e)))?;
+        split_paths(&input, cli.null_delimited)?
+    } else {
+        cli.path_filters.clone()
+    };
 
-        std::fs::rename(&new_file_path, &file_path)?;
+    if cli.repos.is_empty() {
+        let repo = open_repository_from_env()?;
+        return run_repository(cli, &repo, None, &path_filters, out);
     }
 
-    Ok(())
+    let mut outcome = Outcome::Clean;
+    let mut error_codes: Vec<i32> = Vec::new();
+
+    for path in &cli.repos {
+        let label = path.display().to_string();
+        let result = Repository::open(path)
+            .map_err(RTrimError::from)
+            .and_then(|repo| run_repository(cli, &repo, Some(&label), &path_filters, out));
+
+        match result {
+            Ok(repo_outcome) => outcome = outcome.max(repo_outcome),
+            Err(e) => {
+                out.error(format_args!("{}: {}", label, e));
+                error_codes.push(e.exit_code());
+            }
+        }
+    }
+
+    match error_codes.iter().max() {
+        Some(&exit_code) => Err(RTrimError::ReposFailed(error_codes.len(), exit_code)),
+        None => Ok(outcome),
+    }
 }
 
-fn add_files<'a, T>(repo: &Repository, files: T) -> Result<(), git2::Error>
-where
-    T: Iterator<Item = &'a String>,
-{
-    let mut index = repo.index()?;
+/// The repository of the current directory. Like in git, `GIT_DIR` and `GIT_WORK_TREE` override the discovered one
+/// and `GIT_INDEX_FILE` the index, hooks get them from `git commit`. With `--only` or paths the index is a temporary
+/// one with what is about to be committed.
+fn open_repository_from_env() -> Result<Repository, RTrimError> {
+    let working_dir = env::current_dir()?;
+
+    let repo = match env::var_os("GIT_DIR") {
+        Some(git_dir) => {
+            log::debug!("GIT_DIR {}", Path::new(&git_dir).display());
+            Repository::open(working_dir.join(git_dir))?
+        }
+        None => Repository::discover(&working_dir)?,
+    };
+
+    if let Some(work_tree) = env::var_os("GIT_WORK_TREE") {
+        log::debug!("GIT_WORK_TREE {}", Path::new(&work_tree).display());
+        repo.set_workdir(&working_dir.join(work_tree), false)?;
+    }
 
-    for file in files {
-        index.add_path(PathBuf::from(file).as_path())?;
+    if let Some(index_file) = env::var_os("GIT_INDEX_FILE") {
+        log::debug!("GIT_INDEX_FILE {}", Path::new(&index_file).display());
+        let mut index = Index::open(&working_dir.join(index_file))?;
+        repo.set_index(&mut index)?;
     }
 
-    index.write()?;
+    Ok(repo)
+}
 
-    Ok(())
+/// The configuration of a repository:
+/// command line > local git config > .rtrim.toml > global git config > environment > defaults
+fn effective_config(cli: &Cli, repo: &Repository, repo_workdir: &Path, out: &Output) -> Result<Config, RTrimError> {
+    let git_config = repo.config()?;
+    let (global_git_config, local_git_config) = Config::from_git(&git_config, out)?;
+
+    // the generic git setting gives way to every rtrim specific one
+    Ok(Config::from_core_whitespace(&git_config, out)?
+        .merge(environment::from_env(out)?)
+        .merge(global_git_config)
+        .merge(Config::load(repo_workdir)?)
+        .merge(local_git_config)
+        .merge(Config::from_cli(cli)))
 }
 
-fn run(args: &Vec<String>) -> Result<(), RTrimError> {
+/// Processes one repository and its submodules, `label` names it in the summaries when several are processed.
+fn run_repository(
+    cli: &Cli,
+    repo: &Repository,
+    label: Option<&str>,
+    path_filters: &[String],
+    out: &Output,
+) -> Result<Outcome, RTrimError> {
     let working_dir = env::current_dir()?;
-    let repo = Repository::discover(&working_dir)?;
-    let path_filters = &args[1..args.len()];
 
     let repo_workdir = if let Some(repo_workdir) = repo.workdir() {
         repo_workdir
@@ -176,33 +287,580 @@ fn run(args: &Vec<String>) -> Result<(), RTrimError> {
         &working_dir
     };
 
-    let files = get_staged_lines_with_trailing_spaces(&repo, &path_filters)?;
+    log::info!(
+        "repository {}, {} {}",
+        repo.path().display(),
+        if repo.is_worktree() { "linked worktree" } else { "workdir" },
+        repo_workdir.display()
+    );
 
-    rtrim_files(repo_workdir, &files)?;
-    add_files(&repo, files.keys())?;
+    let config = effective_config(cli, repo, repo_workdir, out)?;
 
-    Ok(())
+    if cli.show_config {
+        out.report(config.to_toml().trim_end());
+        return Ok(Outcome::Clean);
+    }
+
+    log::debug!("effective config: {}", config.to_toml().trim_end().replace('\n', ", "));
+
+    let mut cli = cli.clone();
+    config.apply(&mut cli)?;
+    if cli.fix {
+        cli.check = false;
+    }
+    environment::apply_ci_detection(&mut cli, out);
+    let cli = &cli;
+
+    // checking the staged or tracked blobs is all that works without the files
+    let needs_workdir = !cli.check || cli.diff || cli.working_tree || cli.include_untracked;
+    if repo.is_bare() && needs_workdir {
+        return Err(RTrimError::BareRepository(repo.path().to_path_buf()));
+    }
+
+    // the pathspecs on the command line are relative to the current directory, the listed paths aren't
+    let prefixed_paths;
+    let path_filters = if cli.paths_from_stdin || cli.file_list.is_some() {
+        path_filters
+    } else {
+        prefixed_paths = pathspec::relative_to_root(path_filters, &current_dir_prefix(&working_dir, repo_workdir))?;
+        &prefixed_paths
+    };
+
+    let listed_paths;
+    let path_filters = if cli.file_list.is_some() {
+        listed_paths = listed_pathspecs(cli, repo, repo_workdir, path_filters)?;
+        &listed_paths
+    } else {
+        path_filters
+    };
+
+    if cli.stdout {
+        return print_trimmed_content(cli, repo, repo_workdir, path_filters, out);
+    }
+
+    let color = color::enabled(cli.color);
+    let preview = cli.check || cli.diff;
+
+    let mut runs = vec![(String::new(), process_repository(cli, repo, repo_workdir, path_filters, out)?)];
+    let mut failed_submodules = 0;
+
+    if cli.recurse_submodules {
+        failed_submodules = process_submodules(cli, repo, "", path_filters, out, &mut runs)?;
+    }
+
+    // paths relative to the superproject's working tree from here on
+    let mut combined = RepoRun::default();
+    for (prefix, run) in &runs {
+        combined.extend(prefix, run);
+    }
+
+    // the commit would still have their trailing whitespaces
+    let untrimmed =
+        combined.skipped.iter().any(|skipped| matches!(skipped.reason, SkipReason::Unstaged | SkipReason::Missing));
+
+    let report = Report::new(
+        repo_workdir,
+        combined.files_scanned,
+        &combined.files,
+        &combined.stats,
+        std::mem::take(&mut combined.skipped),
+    );
+
+    if cli.diff {
+        print_diffs(repo_workdir, &combined.files, cli.whole_file || cli.all, &Rules::from_cli(cli), color, out)?;
+    }
+
+    if (runs.len() == 1 && label.is_none()) || cli.list_files {
+        print_report(cli, &report, color, out)?;
+    } else {
+        write_report(cli, &report, color, out)?;
+        print_stat(cli, &report, out);
+
+        for (prefix, run) in runs {
+            let name = match (label, prefix.trim_end_matches('/')) {
+                (None, "") => String::from("."),
+                (None, submodule) => submodule.to_string(),
+                (Some(label), "") => label.to_string(),
+                (Some(label), submodule) => format!("{}/{}", label.trim_end_matches('/'), submodule),
+            };
+            let report = Report::new(repo_workdir, run.files_scanned, &run.files, &run.stats, run.skipped);
+            out.info(report.repository_summary(preview, &name));
+        }
+    }
+
+    let RepoRun { files, stats, declined, failed, .. } = combined;
+
+    if !declined.is_empty() {
+        let declined: Vec<String> = declined.iter().map(|file_name| display_path(file_name)).collect();
+        out.info(format_args!("rtrim: left untouched: {}", declined.join(", ")));
+    }
+
+    if cli.index_only && stats.files_modified() > 0 {
+        let rewritten: Vec<String> = stats.files.keys().map(|file_name| display_path(file_name)).collect();
+        out.info(format_args!("rtrim: rewrote the index entries of: {}", rewritten.join(", ")));
+    }
+
+    if cli.no_add && stats.files_modified() > 0 {
+        out.info("rtrim: the trimmed files were not re-staged, review them and run `git add`");
+    }
+
+    if !failed.is_empty() {
+        Err(RTrimError::Failed(failed.len()))
+    } else if failed_submodules > 0 {
+        Err(RTrimError::SubmodulesFailed(failed_submodules))
+    } else if (preview && !files.is_empty()) || untrimmed {
+        Ok(Outcome::Found)
+    } else if stats.files_modified() > 0 {
+        Ok(Outcome::Modified)
+    } else {
+        Ok(Outcome::Clean)
+    }
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// What processing one repository found and did, the paths are relative to its working tree.
+#[derive(Default)]
+struct RepoRun {
+    files: StagedLines,
+    files_scanned: usize,
+    stats: TrimStats,
+    skipped: Vec<SkippedFile>,
+    declined: Vec<PathBuf>,
+    failed: Vec<PathBuf>,
+}
 
-    match run(&args) {
-        Ok(_) => {}
-        Err(err) => {
+impl RepoRun {
+    /// Adds the results of `other` with `prefix` prepended to its paths.
+    fn extend(&mut self, prefix: &str, other: &RepoRun) {
+        let prefixed = |path: &PathBuf| Path::new(prefix).join(path);
+
+        self.files.extend(other.files.iter().map(|(path, findings)| (prefixed(path), findings.clone())));
+        self.files_scanned += other.files_scanned;
+        self.stats.files.extend(other.stats.files.iter().map(|(path, lines)| (prefixed(path), lines.clone())));
+        self.stats.line_endings.extend(other.stats.line_endings.iter().map(|(path, count)| (prefixed(path), *count)));
+        self.stats.bytes_removed += other.stats.bytes_removed;
+        self.skipped.extend(other.skipped.iter().map(|skipped| SkippedFile {
+            path: prefixed(&skipped.file_name).to_string_lossy().into_owned(),
+            file_name: prefixed(&skipped.file_name),
+            lines: skipped.lines.clone(),
+            reason: skipped.reason,
+        }));
+        self.declined.extend(other.declined.iter().map(prefixed));
+        self.failed.extend(other.failed.iter().map(prefixed));
+    }
+}
 
-            // ToDo can I avoid this allocation?
-            let error_message = match err {
-                RTrimError::Git(ge) => {
-                    ge.message().to_string()
-                },
+/// The current directory relative to the working tree, empty at its root or outside of it.
+fn current_dir_prefix(working_dir: &Path, repo_workdir: &Path) -> String {
+    let (Ok(working_dir), Ok(repo_workdir)) = (working_dir.canonicalize(), repo_workdir.canonicalize()) else {
+        return String::new();
+    };
 
-                RTrimError::Io(ioe) => {
-                    ioe.to_string()
+    working_dir
+        .strip_prefix(&repo_workdir)
+        .map(|prefix| prefix.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default()
+}
+
+/// Turns the paths of `--file-list` into literal pathspecs relative to the repository root,
+/// absolute paths have to be inside the working tree.
+fn listed_pathspecs(cli: &Cli, repo: &Repository, repo_workdir: &Path, paths: &[String]) -> Result<Vec<String>, RTrimError> {
+    let canonical_workdir = repo_workdir.canonicalize()?;
+    let index = repo.index()?;
+    let mut pathspecs: Vec<String> = Vec::new();
+    let mut missing: Vec<&str> = Vec::new();
+
+    for path in paths {
+        let relative = if Path::new(path).is_absolute() {
+            let absolute = Path::new(path);
+            let inside = absolute
+                .strip_prefix(repo_workdir)
+                .ok()
+                .or_else(|| absolute.strip_prefix(&canonical_workdir).ok());
+
+            match inside {
+                Some(relative) => relative.to_string_lossy().into_owned(),
+                None => {
+                    return Err(RTrimError::Config(format!("{} is outside the repository", path)));
                 }
-            };
+            }
+        } else {
+            path.clone()
+        };
+
+        if cli.require_listed
+            && index.get_path(Path::new(&relative), 0).is_none()
+            && !repo_workdir.join(&relative).exists()
+        {
+            missing.push(path);
+        }
+
+        pathspecs.push(format!(":(literal){}", relative));
+    }
+
+    if !missing.is_empty() {
+        return Err(RTrimError::Config(format!("listed files not found: {}", missing.join(", "))));
+    }
+
+    Ok(pathspecs)
+}
+
+/// Scans a repository for the lines to trim, without the files skipped by the filters.
+fn scan_repository(cli: &Cli, repo: &Repository, path_filters: &[String], out: &Output) -> Result<Scan, RTrimError> {
+    let base = match (&cli.against, cli.amend) {
+        (Some(rev), _) => Base::Rev(rev),
+        (None, true) => Base::Amend,
+        (None, false) => Base::Head,
+    };
+
+    let rules = Rules::from_cli(cli);
+    let options = ScanOptions {
+        base,
+        path_filters,
+        whole_file: cli.whole_file,
+        working_tree: cli.working_tree,
+        include_untracked: cli.include_untracked,
+        include_intent_to_add: cli.include_intent_to_add,
+        // only rewriting is limited, check mode still flags large files
+        max_file_size: cli.max_file_size.filter(|size| *size > 0 && !cli.check),
+        force_text: &cli.force_text,
+        rules: &rules,
+    };
+
+    // an empty pathspec list matches everything, but an empty stdin or file list is supposed to match nothing
+    let mut scan = if (cli.paths_from_stdin || cli.file_list.is_some()) && path_filters.is_empty() {
+        Scan {
+            files: BTreeMap::new(),
+            files_scanned: 0,
+            skipped: Vec::new(),
+        }
+    } else if cli.all {
+        get_tracked_lines_with_trailing_spaces(repo, &options, out)?
+    } else {
+        get_staged_lines_with_trailing_spaces(repo, &options, out)?
+    };
 
-            eprintln!("error {}", error_message);
+    scan.skipped.extend(filter::apply(&mut scan.files, &cli.file_filters, out));
+    if let Some(suffix) = cli.backup_suffix() {
+        scan.skipped.extend(filter::skip_backups(&mut scan.files, suffix, out));
+    }
+    if let Some(workdir) = repo.workdir().filter(|_| cli.editorconfig) {
+        scan.skipped.extend(filter::skip_editorconfig(&mut scan.files, workdir, out));
+    }
+
+    Ok(scan)
+}
+
+/// `--stdout` mode: prints the content of the single given file with its flagged lines trimmed.
+/// Binary content is printed unmodified.
+fn print_trimmed_content(
+    cli: &Cli,
+    repo: &Repository,
+    repo_workdir: &Path,
+    path_filters: &[String],
+    out: &Output,
+) -> Result<Outcome, RTrimError> {
+    let [pathspec] = path_filters else {
+        return Err(RTrimError::Config(String::from("--stdout needs exactly one file")));
+    };
+    let file_name = Path::new(pathspec.strip_prefix(":(literal)").unwrap_or(pathspec));
+
+    let content = fs::read(repo_workdir.join(file_name))?;
+    let Scan { files, .. } = scan_repository(cli, repo, path_filters, out)?;
+
+    let findings = files.get(file_name).map(Vec::as_slice).unwrap_or_default();
+
+    let trimmed = if findings.is_empty() || scan::is_binary_unless_utf16(&content, cli.utf16) {
+        content.clone()
+    } else {
+        let rules = Rules::from_cli(cli).for_file(repo_workdir, file_name);
+        let mut trimmed_file = TrimmedFile::parse(&content, flagged_lines(findings), &rules)?;
+        fix_file_findings(&mut trimmed_file, findings, &rules);
+
+        let mut trimmed = Vec::with_capacity(content.len());
+        trimmed_file.write_to(&mut trimmed)?;
+        trimmed
+    };
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&trimmed)?;
+    stdout.flush()?;
+
+    if trimmed == content {
+        Ok(Outcome::Clean)
+    } else {
+        Ok(Outcome::Modified)
+    }
+}
+
+/// Scans and trims the staged changes of one repository.
+fn process_repository(
+    cli: &Cli,
+    repo: &Repository,
+    repo_workdir: &Path,
+    path_filters: &[String],
+    out: &Output,
+) -> Result<RepoRun, RTrimError> {
+    let Scan { mut files, files_scanned, mut skipped } = scan_repository(cli, repo, path_filters, out)?;
+
+    let mut declined: Vec<PathBuf> = Vec::new();
+    if cli.interactive {
+        (files, declined) = interactive::select_files(out, files)?;
+    }
+
+    let preview = cli.check || cli.diff;
+    // the findings of --all already cover every line of the staged content
+    let whole_file = cli.whole_file || cli.all;
+    let rules = Rules::from_cli(cli);
+
+    let (stats, failed) = if preview {
+        (TrimStats::default(), Vec::new())
+    } else if cli.index_only {
+        index::trim_index_entries(repo, &files, &rules, out)?
+    } else if cli.working_tree {
+        rtrim_files(repo_workdir, &files, whole_file, &rules, cli.backup_suffix(), out)
+    } else {
+        // their working tree files are left as they are, only the staged content is trimmed
+        let mut worktree_ignored = StagedLines::new();
+        for (file_name, bit) in index::worktree_ignored(repo, &files)? {
+            if cli.no_add {
+                out.verbose(format_args!("{}: {} is set, left alone with --no-add", display_path(&file_name), bit));
+            } else {
+                out.verbose(format_args!("{}: {} is set, trimmed in the index only", display_path(&file_name), bit));
+            }
+            worktree_ignored.extend(files.remove_entry(&file_name));
+        }
+
+        // nothing of them is staged, their working tree file is trimmed and they stay intent-to-add
+        let intent_to_add = index::intent_to_add(repo, &files)?;
+        files.retain(|file_name, _| !intent_to_add.contains_key(file_name));
+
+        // deleted after staging or left out by a sparse checkout, only --index-only can trim them
+        let missing: Vec<PathBuf> = files
+            .keys()
+            .filter(|file_name| {
+                fs::symlink_metadata(working_tree_path(repo_workdir, file_name))
+                    .is_err_and(|e| e.kind() == io::ErrorKind::NotFound)
+            })
+            .cloned()
+            .collect();
+        for file_name in &missing {
+            out.warning(format_args!(
+                "{}: skipped, missing from the working tree, use --index-only to trim it",
+                display_path(file_name)
+            ));
+            skipped.push(SkippedFile::new(file_name, &files[file_name], SkipReason::Missing));
+            files.remove(file_name);
+        }
+
+        // adding a partially staged file would stage its unstaged changes, its staged content is trimmed in the
+        // index and the working tree file only where it still matches
+        let partial = index::partially_staged(repo, &files);
+        let partial = if cli.force_dirty {
+            partial
+        } else {
+            for (file_name, findings) in &partial {
+                out.warning(format_args!(
+                    "{}: skipped, unstaged changes present, use --force-dirty to trim it",
+                    display_path(file_name)
+                ));
+                skipped.push(SkippedFile::new(file_name, findings, SkipReason::Unstaged));
+            }
+            files.retain(|file_name, _| !partial.contains_key(file_name));
+            StagedLines::new()
+        };
+        let mut fully_staged = files.clone();
+        fully_staged.retain(|file_name, _| !partial.contains_key(file_name));
+
+        let (mut stats, mut failed) =
+            rtrim_files(repo_workdir, &fully_staged, whole_file, &rules, cli.backup_suffix(), out);
+        // unstaged changes stay unstaged
+        if !cli.no_add {
+            add_files(repo, fully_staged.keys().filter(|file_name| !failed.contains(file_name)))?;
+        }
+
+        if !partial.is_empty() {
+            for file_name in partial.keys() {
+                out.verbose(format_args!(
+                    "{}: partially staged, the unstaged changes are kept",
+                    display_path(file_name)
+                ));
+            }
+            let working_tree = index::working_tree_findings(repo, &partial)?;
+            let (working_tree_stats, working_tree_failed) =
+                rtrim_files(repo_workdir, &working_tree, false, &rules, cli.backup_suffix(), out);
+            failed.extend(working_tree_failed);
+
+            stats.extend(working_tree_stats);
+            if !cli.no_add {
+                let (index_stats, index_failed) = index::trim_index_entries(repo, &partial, &rules, out)?;
+                stats.extend(index_stats);
+                for file_name in index_failed {
+                    if !failed.contains(&file_name) {
+                        failed.push(file_name);
+                    }
+                }
+            }
+        }
+
+        if !intent_to_add.is_empty() {
+            let (intent_to_add_stats, intent_to_add_failed) =
+                rtrim_files(repo_workdir, &intent_to_add, true, &rules, cli.backup_suffix(), out);
+            stats.extend(intent_to_add_stats);
+            failed.extend(intent_to_add_failed);
+            files.extend(intent_to_add);
+        }
+
+        if !worktree_ignored.is_empty() {
+            if !cli.no_add {
+                let (index_stats, index_failed) = index::trim_index_entries(repo, &worktree_ignored, &rules, out)?;
+                stats.extend(index_stats);
+                failed.extend(index_failed);
+            }
+            files.extend(worktree_ignored);
+        }
+        (stats, failed)
+    };
+
+    Ok(RepoRun {
+        files,
+        files_scanned,
+        stats,
+        skipped,
+        declined,
+        failed,
+    })
+}
+
+/// The pathspecs that apply inside the submodule at `path`, `None` if none does.
+fn submodule_path_filters(path_filters: &[String], path: &str) -> Option<Vec<String>> {
+    if path_filters.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let mut filters: Vec<String> = Vec::new();
+
+    for path_filter in path_filters {
+        match path_filter.strip_prefix(path) {
+            Some("") | Some("/") => return Some(Vec::new()),
+            Some(rest) if rest.starts_with('/') => filters.push(rest[1..].to_string()),
+            _ => {}
+        }
+    }
+
+    (!filters.is_empty()).then_some(filters)
+}
+
+/// Processes the initialized submodules of `repo` recursively, a failing submodule is reported and doesn't
+/// stop the others. Returns the number of failed submodules.
+fn process_submodules(
+    cli: &Cli,
+    repo: &Repository,
+    prefix: &str,
+    path_filters: &[String],
+    out: &Output,
+    runs: &mut Vec<(String, RepoRun)>,
+) -> Result<usize, RTrimError> {
+    let mut failed = 0;
+
+    for submodule in repo.submodules()? {
+        let name = format!("{}{}", prefix, submodule.path().to_string_lossy());
+
+        let Some(path_filters) = submodule_path_filters(path_filters, &submodule.path().to_string_lossy()) else {
+            log::debug!("{}: no pathspec matches, skipped", name);
+            continue;
+        };
+
+        let sub_repo = match submodule.open() {
+            Ok(sub_repo) => sub_repo,
+            Err(_) => {
+                out.verbose(format_args!("{}: submodule is not initialized, skipped", name));
+                continue;
+            }
+        };
+
+        let Some(sub_workdir) = sub_repo.workdir() else {
+            continue;
+        };
+
+        log::info!("submodule {}, workdir {}", name, sub_workdir.display());
+        let sub_prefix = format!("{}/", name);
+
+        match process_repository(cli, &sub_repo, sub_workdir, &path_filters, out) {
+            Ok(run) => runs.push((sub_prefix.clone(), run)),
+            Err(e) => {
+                out.error(format_args!("{}: {}", name, e));
+                failed += 1;
+                continue;
+            }
+        }
+
+        failed += process_submodules(cli, &sub_repo, &sub_prefix, &path_filters, out, runs)?;
+    }
+
+    Ok(failed)
+}
+
+/// Parses the command line, a path starting with a dash that was taken for a flag gets a hint about `--`.
+fn parse_cli() -> Cli {
+    Cli::try_parse().unwrap_or_else(|mut e| {
+        if e.kind() == ErrorKind::UnknownArgument {
+            let dash_path = env::args()
+                .skip(1)
+                .take_while(|arg| arg != "--")
+                .find(|arg| arg.starts_with('-') && Path::new(arg).exists());
+
+            if let Some(path) = dash_path {
+                let hint = format!(
+                    "`{}` is a path, pass pathspecs starting with a dash after `--`: rtrim [OPTIONS] -- {}",
+                    path, path
+                );
+                e.insert(ContextKind::Suggested, ContextValue::StyledStrs(vec![hint.into()]));
+            }
+        }
+
+        e.exit()
+    })
+}
+
+fn main() {
+    let cli = parse_cli();
+    let out = Output::new(cli.quiet, cli.verbose, cli.no_progress);
+
+    let result = logger::init(cli.log_level).and_then(|()| match &cli.command {
+        Some(Command::Filter { file_filters, path }) => run_filter(file_filters, path.as_deref(), &Rules::from_cli(&cli), &out),
+        Some(Command::Hook { action }) => hook::run_hook(action, &out),
+        Some(Command::CheckRange { range }) => range::run_check_range(range, color::enabled(cli.color), &out),
+        Some(Command::CheckCommit { revs }) => range::run_check_commit(revs, color::enabled(cli.color), &out),
+        Some(Command::PrePush { remote, .. }) => range::run_pre_push(remote.as_deref(), color::enabled(cli.color), &out),
+        Some(Command::CommitMsg { path, trim_comments }) => commit_msg::run_commit_msg(path, *trim_comments, &out),
+        Some(Command::FixHead) => fix_head::run_fix_head(&cli, &out),
+        Some(Command::Rewrite { range, file_filters, branch, force }) => {
+            rewrite::run_rewrite(range, branch.as_deref(), *force, file_filters, &out)
+        }
+        Some(Command::Watch { debounce }) => watch::run_watch(&cli, *debounce, &out),
+        Some(Command::Doctor) => doctor::run_doctor(&cli, &out),
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(*shell, &mut Cli::command(), "rtrim", &mut io::stdout());
+            Ok(Outcome::Clean)
+        }
+        None if cli.patch.is_some() => {
+            let mut cli = cli.clone();
+            environment::apply_ci_detection(&mut cli, &out);
+            patch::run_patch(&cli, cli.patch.as_deref().unwrap_or(Path::new("-")), &out)
+        }
+        None if cli.files => {
+            let mut cli = cli.clone();
+            environment::apply_ci_detection(&mut cli, &out);
+            plain::run_plain_files(&cli, &out)
+        }
+        None => run(&cli, &out),
+    });
+
+    match result {
+        Ok(outcome) => process::exit(outcome.exit_code()),
+        Err(err) => {
+            out.error(&err);
+            process::exit(err.exit_code());
         }
     }
 }
@@ -0,0 +1,206 @@
+use std::cell::Cell;
+use std::fmt::Display;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two redraws of a progress counter.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// A path for output, quoted like git quotes it with core.quotePath off if it has special characters or isn't
+/// valid UTF-8, `"caf\351.txt"`.
+pub fn display_path(path: &Path) -> String {
+    let bytes = path.as_os_str().as_encoded_bytes();
+    let needs_quotes = |c: char| c == '"' || c == '\\' || c.is_ascii_control();
+
+    match std::str::from_utf8(bytes) {
+        Ok(path) if !path.contains(needs_quotes) => path.to_string(),
+        _ => {
+            let mut quoted = String::from("\"");
+            for chunk in bytes.utf8_chunks() {
+                for c in chunk.valid().chars() {
+                    match c {
+                        '"' | '\\' => quoted.extend(['\\', c]),
+                        '\t' => quoted.push_str("\\t"),
+                        '\n' => quoted.push_str("\\n"),
+                        '\r' => quoted.push_str("\\r"),
+                        c if c.is_ascii_control() => quoted.push_str(&format!("\\{:03o}", c as u8)),
+                        c => quoted.push(c),
+                    }
+                }
+                for byte in chunk.invalid() {
+                    quoted.push_str(&format!("\\{:03o}", byte));
+                }
+            }
+            quoted.push('"');
+            quoted
+        }
+    }
+}
+
+/// All user facing output goes through here, so quiet/normal/verbose are handled in one place.
+/// Reports go to stdout, everything else to stderr.
+pub struct Output {
+    verbosity: Verbosity,
+    progress: bool,
+    /// A progress counter is drawn on the current stderr line and has to be cleared first.
+    progress_shown: Cell<bool>,
+}
+
+impl Output {
+    pub fn new(quiet: bool, verbose: bool, no_progress: bool) -> Self {
+        // quiet wins over verbose
+        let verbosity = if quiet {
+            Verbosity::Quiet
+        } else if verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        };
+
+        Output {
+            verbosity,
+            progress: !quiet && !no_progress && io::stderr().is_terminal(),
+            progress_shown: Cell::new(false),
+        }
+    }
+
+    fn clear_progress(&self) {
+        if self.progress_shown.replace(false) {
+            eprint!("\r\x1b[K");
+        }
+    }
+
+    pub fn report(&self, msg: impl Display) {
+        if self.verbosity >= Verbosity::Normal {
+            self.clear_progress();
+            println!("{}", msg);
+        }
+    }
+
+    /// Like `report` but without appending a newline.
+    pub fn report_raw(&self, msg: impl Display) {
+        if self.verbosity >= Verbosity::Normal {
+            self.clear_progress();
+            print!("{}", msg);
+        }
+    }
+
+    /// Like `report_raw` for content that isn't necessarily valid UTF-8, e.g. a diff.
+    pub fn report_bytes(&self, bytes: &[u8]) {
+        if self.verbosity >= Verbosity::Normal {
+            self.clear_progress();
+            _ = io::stdout().lock().write_all(bytes);
+        }
+    }
+
+    pub fn info(&self, msg: impl Display) {
+        if self.verbosity >= Verbosity::Normal {
+            self.clear_progress();
+            eprintln!("{}", msg);
+        }
+    }
+
+    pub fn verbose(&self, msg: impl Display) {
+        if self.verbosity >= Verbosity::Verbose {
+            self.clear_progress();
+            eprintln!("{}", msg);
+        }
+    }
+
+    pub fn warning(&self, msg: impl Display) {
+        if self.verbosity >= Verbosity::Normal {
+            self.clear_progress();
+            eprintln!("warning {}", msg);
+        }
+    }
+
+    /// Interactive prompts are always shown, stderr is not buffered so no flush is needed.
+    pub fn prompt(&self, msg: impl Display) {
+        self.clear_progress();
+        eprint!("{}", msg);
+    }
+
+    pub fn error(&self, msg: impl Display) {
+        self.clear_progress();
+        eprintln!("error {}", msg);
+    }
+
+    /// A `label: n/total` counter on stderr, only drawn if stderr is a terminal.
+    pub fn progress(&self, label: &'static str, total: usize) -> Progress<'_> {
+        Progress {
+            out: self,
+            label,
+            total,
+            current: 0,
+            last_draw: None,
+        }
+    }
+}
+
+pub struct Progress<'a> {
+    out: &'a Output,
+    label: &'static str,
+    total: usize,
+    current: usize,
+    last_draw: Option<Instant>,
+}
+
+impl Progress<'_> {
+    pub fn tick(&mut self) {
+        self.current += 1;
+
+        if !self.out.progress {
+            return;
+        }
+
+        let due = self
+            .last_draw
+            .is_none_or(|last_draw| last_draw.elapsed() >= PROGRESS_INTERVAL);
+
+        if due || self.current == self.total {
+            eprint!("\r{}: {}/{} files", self.label, self.current, self.total);
+            self.out.progress_shown.set(true);
+            self.last_draw = Some(Instant::now());
+        }
+    }
+}
+
+impl Drop for Progress<'_> {
+    fn drop(&mut self) {
+        self.out.clear_progress();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::bytes_path;
+
+    #[test]
+    fn plain_paths_are_not_quoted() {
+        assert_eq!(display_path(Path::new("src/main.rs")), "src/main.rs");
+        assert_eq!(display_path(Path::new("caf\u{e9} file.txt")), "caf\u{e9} file.txt");
+    }
+
+    #[test]
+    fn special_paths_are_quoted_like_git() {
+        assert_eq!(display_path(Path::new("a\"b")), r#""a\"b""#);
+        assert_eq!(display_path(Path::new("a\\b")), r#""a\\b""#);
+        assert_eq!(display_path(Path::new("a\tb\n")), r#""a\tb\n""#);
+        assert_eq!(display_path(Path::new("a\x1b")), r#""a\033""#);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn invalid_utf8_is_escaped_in_octal() {
+        assert_eq!(display_path(&bytes_path(b"caf\xe9.txt")), r#""caf\351.txt""#);
+    }
+}
@@ -0,0 +1,350 @@
+use git2::Repository;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::cli::Cli;
+use crate::error::RTrimError;
+use crate::output::Output;
+use crate::report::Report;
+use crate::rules::Rules;
+use crate::scan::{bytes_path, line_findings, Finding, FindingKind, StagedLines};
+use crate::trim::split_line_ending;
+use crate::trim::TrimStats;
+use crate::fix::{print_diffs, rtrim_files};
+use crate::{color, filter, print_report, Outcome};
+
+/// Undoes git's C-style quoting of paths with special characters, `"a\tb"` → `a<TAB>b`.
+fn unquote(path: &str) -> PathBuf {
+    let Some(quoted) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return PathBuf::from(path);
+    };
+
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut chars = quoted.bytes().peekable();
+
+    while let Some(b) = chars.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+
+        match chars.next() {
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(digit @ b'0'..=b'7') => {
+                // three octal digits per byte of a UTF-8 sequence
+                let mut value = (digit - b'0') as u32;
+                for _ in 0..2 {
+                    if let Some(next @ b'0'..=b'7') = chars.peek().copied() {
+                        value = value * 8 + (next - b'0') as u32;
+                        chars.next();
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => bytes.push(other),
+            None => bytes.push(b'\\'),
+        }
+    }
+
+    bytes_path(&bytes)
+}
+
+/// The path of a `---`/`+++` header without the `a/`/`b/` prefix, `None` for `/dev/null`.
+/// An unquoted path is taken byte for byte, e.g. from a diff tool that doesn't quote non-UTF-8 names.
+fn header_path(header: &[u8], prefix: &str) -> Option<PathBuf> {
+    // a tab separates an optional timestamp
+    let header = header.split(|b| *b == b'\t').next().unwrap_or(header).trim_ascii_end();
+    let path = match header.first() {
+        Some(b'"') => unquote(&String::from_utf8_lossy(header)),
+        _ => bytes_path(header),
+    };
+
+    if path == Path::new("/dev/null") {
+        return None;
+    }
+
+    Some(path.strip_prefix(prefix).map(Path::to_path_buf).unwrap_or(path))
+}
+
+/// The start line and the line count of a `l,s` range, the count is 1 if it is left out.
+fn hunk_range(range: &str) -> Option<(u32, u32)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// The old line count, the new start line and the new line count of a `@@ -l,s +l,s @@` hunk header without
+/// its leading `@@ `. `None` if it is malformed or its lines don't fit in a line number.
+fn hunk_header(header: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = header.split_whitespace();
+    let (_, old_count) = hunk_range(parts.next()?.strip_prefix('-')?)?;
+    let (new_start, new_count) = hunk_range(parts.next()?.strip_prefix('+')?)?;
+    if parts.next() != Some("@@") {
+        return None;
+    }
+    new_start.checked_add(new_count)?;
+
+    Some((old_count, new_start, new_count))
+}
+
+fn malformed_hunk(patch_line: usize, header: &[u8]) -> RTrimError {
+    RTrimError::Patch(format!("line {}: malformed hunk header: {}", patch_line, String::from_utf8_lossy(header)))
+}
+
+/// Parses a unified diff and returns the added lines with trailing whitespaces per new file path and the
+/// number of files in the patch. Deleted files are ignored, renamed files are reported under their new path.
+/// A malformed hunk header is an error, the lines after it couldn't be numbered.
+pub fn parse_patch(patch: &[u8], rules: &Rules) -> Result<(StagedLines, usize), RTrimError> {
+    let mut result: StagedLines = BTreeMap::new();
+    let mut files = 0;
+
+    let mut path: Option<PathBuf> = None;
+    let mut file_rules: Option<Rules> = None;
+    let mut new_line_no: u32 = 0;
+    // lines left in the current hunk, old and new side
+    let mut remaining: (u32, u32) = (0, 0);
+    // the 1-based line of the current hunk header in the patch and the header, for errors
+    let mut hunk_start = (0, &b""[..]);
+
+    // the added lines keep their line endings, a CRLF or a stray CR before it is part of the line
+    for (patch_line, raw_line) in (1..).zip(patch.split_inclusive(|b| *b == b'\n')) {
+        let (line, _) = split_line_ending(raw_line);
+        if remaining.0 > 0 || remaining.1 > 0 {
+            match line.first() {
+                Some(b'+') => {
+                    if let (Some(path), Some(rules)) = (&path, &file_rules) {
+                        let added = &raw_line[1..];
+                        let mut findings = line_findings(new_line_no, added, rules);
+                        let (content, line_ending) = split_line_ending(added);
+                        let wrong_ending =
+                            rules.eol.is_some_and(|eol| !line_ending.is_empty() && line_ending != eol.as_bytes());
+                        // like in the staged files only the first wrong line ending of a file is reported
+                        let reported = result.get(path).is_some_and(|findings| {
+                            findings.iter().any(|f| f.kind == FindingKind::LineEnding)
+                        });
+                        if wrong_ending && !reported {
+                            findings.push(Finding::wrong_line_ending(new_line_no, content));
+                        }
+                        if !findings.is_empty() {
+                            result.entry(path.clone()).or_default().extend(findings);
+                        }
+                    }
+                    new_line_no =
+                        new_line_no.checked_add(1).ok_or_else(|| malformed_hunk(hunk_start.0, hunk_start.1))?;
+                    remaining.1 = remaining.1.saturating_sub(1);
+                }
+                Some(b'-') => remaining.0 = remaining.0.saturating_sub(1),
+                Some(b'\\') => {}
+                // an empty line is a context line whose space was stripped by an editor or mail client
+                Some(b' ') | None => {
+                    new_line_no =
+                        new_line_no.checked_add(1).ok_or_else(|| malformed_hunk(hunk_start.0, hunk_start.1))?;
+                    remaining = (remaining.0.saturating_sub(1), remaining.1.saturating_sub(1));
+                }
+                Some(_) => remaining = (0, 0),
+            }
+
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix(b"+++ ") {
+            path = header_path(header, "b/");
+            file_rules = path.as_deref().map(|path| rules.for_path(path));
+            if path.is_some() {
+                files += 1;
+            }
+        } else if let Some(header) = line.strip_prefix(b"@@ ") {
+            hunk_start = (patch_line, line);
+            let (old_count, new_start, new_count) = std::str::from_utf8(header)
+                .ok()
+                .and_then(hunk_header)
+                .ok_or_else(|| malformed_hunk(patch_line, line))?;
+
+            new_line_no = new_start;
+            remaining = (old_count, new_count);
+        } else if line.starts_with(b"diff ") {
+            path = None;
+        }
+    }
+
+    for findings in result.values_mut() {
+        findings.sort_by_key(|f| f.line);
+    }
+
+    Ok((result, files))
+}
+
+/// `--patch` mode: checks or fixes the lines added by a unified diff instead of the staged changes.
+/// The paths are relative to the repository's working tree, or the current directory outside of one.
+/// The fixed files are not staged.
+pub fn run_patch(cli: &Cli, source: &Path, out: &Output) -> Result<Outcome, RTrimError> {
+    let working_dir = std::env::current_dir()?;
+    let repo = Repository::discover(&working_dir).ok();
+    let workdir = repo
+        .as_ref()
+        .and_then(|repo| repo.workdir())
+        .unwrap_or(&working_dir)
+        .to_path_buf();
+
+    let content = if source == Path::new("-") {
+        let mut content = Vec::new();
+        io::stdin().read_to_end(&mut content)?;
+        content
+    } else {
+        fs::read(source)?
+    };
+
+    let rules = Rules::from_cli(cli);
+    let (mut files, files_scanned) = parse_patch(&content, &rules)?;
+    let skipped = filter::apply(&mut files, &cli.file_filters, out);
+
+    let preview = cli.check || cli.diff;
+    let color = color::enabled(cli.color);
+
+    let (stats, failed) = if preview {
+        (TrimStats::default(), Vec::new())
+    } else {
+        rtrim_files(&workdir, &files, false, &rules, cli.backup_suffix(), out)
+    };
+
+    if cli.diff {
+        print_diffs(&workdir, &files, false, &rules, color, out)?;
+    }
+
+    let report = Report::new(&workdir, files_scanned, &files, &stats, skipped);
+    print_report(cli, &report, color, out)?;
+
+    if !failed.is_empty() {
+        Err(RTrimError::Failed(failed.len()))
+    } else if preview && !files.is_empty() {
+        Ok(Outcome::Found)
+    } else if stats.files_modified() > 0 {
+        Ok(Outcome::Modified)
+    } else {
+        Ok(Outcome::Clean)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(patch: &[u8], rules: &Rules) -> (StagedLines, usize) {
+        parse_patch(patch, rules).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn lines(staged: &StagedLines, path: &str) -> Vec<u32> {
+        staged[Path::new(path)].iter().map(|finding| finding.line).collect()
+    }
+
+    #[test]
+    fn only_added_lines_are_flagged() {
+        let patch = concat!(
+            "diff --git a/a.txt b/a.txt\n",
+            "--- a/a.txt\n",
+            "+++ b/a.txt\n",
+            "@@ -1,3 +1,4 @@\n",
+            " context  \n",
+            "-old \n",
+            "+new  \n",
+            "+kept\n",
+            " more \n",
+        );
+        let (staged, files) = parse(patch.as_bytes(), &Rules::default());
+
+        assert_eq!(files, 1);
+        assert_eq!(lines(&staged, "a.txt"), [2]);
+    }
+
+    #[test]
+    fn deleted_files_are_ignored_and_renamed_files_use_the_new_path() {
+        let patch = concat!(
+            "diff --git a/gone.txt b/gone.txt\n",
+            "--- a/gone.txt\n",
+            "+++ /dev/null\n",
+            "@@ -1 +0,0 @@\n",
+            "-gone \n",
+            "diff --git a/old.txt b/new.txt\n",
+            "--- a/old.txt\n",
+            "+++ b/new.txt\n",
+            "@@ -1 +1,2 @@\n",
+            " a\n",
+            "+b \n",
+        );
+        let (staged, files) = parse(patch.as_bytes(), &Rules::default());
+
+        assert_eq!(files, 1);
+        assert_eq!(staged.keys().collect::<Vec<_>>(), [Path::new("new.txt")]);
+        assert_eq!(lines(&staged, "new.txt"), [2]);
+    }
+
+    #[test]
+    fn quoted_paths_are_unquoted() {
+        assert_eq!(unquote(r#""a\tb""#), Path::new("a\tb"));
+        assert_eq!(unquote(r#""\"q\"""#), Path::new("\"q\""));
+        assert_eq!(unquote(r#""caf\303\251""#), Path::new("caf\u{e9}"));
+        assert_eq!(unquote("plain"), Path::new("plain"));
+        assert_eq!(header_path(br#""b/caf\303\251.txt""#, "b/"), Some(PathBuf::from("caf\u{e9}.txt")));
+        assert_eq!(header_path(b"/dev/null", "b/"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn added_lines_are_bytes() {
+        let patch = b"--- a/caf\xe9.txt\n+++ b/caf\xe9.txt\n@@ -0,0 +1,2 @@\n+caf\xe9 \n+caf\xe9\n";
+        let (staged, _) = parse(patch, &Rules::default());
+
+        assert_eq!(staged.keys().collect::<Vec<_>>(), [&bytes_path(b"caf\xe9.txt")]);
+        assert_eq!(staged[&bytes_path(b"caf\xe9.txt")].iter().map(|f| f.line).collect::<Vec<_>>(), [1]);
+    }
+
+    #[test]
+    fn added_lines_keep_their_line_endings() {
+        let patch = b"+++ b/a.txt\n@@ -0,0 +1,3 @@\n+a\r\r\n+b\r\n+c\r\n";
+
+        let control = Rules { control_whitespace: true, ..Rules::default() };
+        assert_eq!(lines(&parse(patch, &control).0, "a.txt"), [1]);
+
+        let eol = Rules { eol: Some("\n"), ..Rules::default() };
+        let (staged, _) = parse(patch, &eol);
+        let findings = &staged[Path::new("a.txt")];
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].kind == FindingKind::LineEnding);
+        assert_eq!(findings[0].line, 1);
+    }
+
+    #[test]
+    fn hunk_headers_are_parsed() {
+        assert_eq!(hunk_header("-1,3 +1,4 @@"), Some((3, 1, 4)));
+        assert_eq!(hunk_header("-1 +1 @@ fn main() {"), Some((1, 1, 1)));
+        assert_eq!(hunk_header("-0,0 +1 @@"), Some((0, 1, 1)));
+        assert_eq!(hunk_header("-1,1 +0,0 @@"), Some((1, 0, 0)));
+    }
+
+    #[test]
+    fn malformed_hunk_headers_are_errors() {
+        for header in ["@@ -1 +x @@", "@@ -1,2 @@", "@@ +1 -1 @@", "@@ -1 +1", "@@ -1 +4294967295,2 @@"] {
+            let patch = format!("+++ b/a.txt\n{}\n+a \n", header);
+
+            match parse_patch(patch.as_bytes(), &Rules::default()) {
+                Err(RTrimError::Patch(message)) => {
+                    assert_eq!(message, format!("line 2: malformed hunk header: {}", header))
+                }
+                _ => panic!("{}: accepted", header),
+            }
+        }
+    }
+
+    #[test]
+    fn line_numbers_past_the_last_line_are_errors() {
+        // more added lines than the header counts, the old side keeps the hunk going
+        let patch = b"+++ b/a.txt\n@@ -1 +4294967294 @@\n+a\n+b \n-c\n";
+
+        assert!(matches!(parse_patch(patch, &Rules::default()), Err(RTrimError::Patch(_))));
+    }
+}
@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use glob::{MatchOptions, Pattern};
+
+use crate::error::RTrimError;
+
+/// One parsed pathspec with its magic, see gitglossary(7).
+struct Spec {
+    pattern: String,
+    glob: bool,
+    icase: bool,
+    literal: bool,
+    exclude: bool,
+}
+
+impl Spec {
+    fn parse(pathspec: &str) -> Result<Self, RTrimError> {
+        let mut spec = Spec {
+            pattern: String::new(),
+            glob: false,
+            icase: false,
+            literal: false,
+            exclude: false,
+        };
+
+        let pattern = if let Some(long) = pathspec.strip_prefix(":(") {
+            let (magic, pattern) = long
+                .split_once(')')
+                .ok_or_else(|| RTrimError::Config(format!("pathspec `{}`: missing `)`", pathspec)))?;
+
+            for word in magic.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+                match word {
+                    "glob" => spec.glob = true,
+                    "icase" => spec.icase = true,
+                    "literal" => spec.literal = true,
+                    "exclude" => spec.exclude = true,
+                    // the paths are relative to the repository root anyway
+                    "top" => {}
+                    _ => {
+                        return Err(RTrimError::Config(format!(
+                            "pathspec `{}`: unsupported magic `{}`, supported are glob, icase, literal, exclude and top",
+                            pathspec, word
+                        )))
+                    }
+                }
+            }
+
+            pattern
+        } else if let Some(short) = pathspec.strip_prefix(':') {
+            let magic_end = short.find(|c| !matches!(c, '!' | '^' | '/')).unwrap_or(short.len());
+            spec.exclude = short[..magic_end].contains(['!', '^']);
+
+            // `:` ends the short magic, like in `:!:file`
+            let rest = &short[magic_end..];
+            rest.strip_prefix(':').unwrap_or(rest)
+        } else {
+            pathspec
+        };
+
+        if spec.glob && spec.literal {
+            return Err(RTrimError::Config(format!("pathspec `{}`: glob and literal are exclusive", pathspec)));
+        }
+
+        let pattern = pattern.strip_prefix("./").unwrap_or(pattern).trim_end_matches('/');
+        spec.pattern = if pattern == "." { String::new() } else { pattern.to_string() };
+
+        if !spec.literal && Pattern::new(&spec.pattern).is_err() {
+            return Err(RTrimError::Config(format!("pathspec `{}`: invalid pattern", pathspec)));
+        }
+
+        Ok(spec)
+    }
+
+    /// Without magic the pathspec can be handed to libgit2 as it is.
+    fn is_plain(&self) -> bool {
+        !self.glob && !self.icase && !self.literal && !self.exclude
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        if self.pattern.is_empty() {
+            return true;
+        }
+
+        let (pattern, path) = if self.icase {
+            (self.pattern.to_lowercase(), path.to_lowercase())
+        } else {
+            (self.pattern.clone(), path.to_string())
+        };
+
+        // a leading directory matches everything below it
+        if path == pattern || path.starts_with(&format!("{}/", pattern)) {
+            return true;
+        }
+
+        if self.literal {
+            return false;
+        }
+
+        // without glob magic wildcards also match `/`, like fnmatch(3) without FNM_PATHNAME
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: self.glob,
+            require_literal_leading_dot: false,
+        };
+
+        Pattern::new(&pattern).is_ok_and(|p| p.matches_with(&path, options))
+    }
+}
+
+/// Git pathspecs with the magic libgit2 doesn't implement emulated by matching the paths afterwards.
+pub struct Pathspecs {
+    includes: Vec<Spec>,
+    excludes: Vec<Spec>,
+}
+
+impl Pathspecs {
+    pub fn parse(pathspecs: &[String]) -> Result<Self, RTrimError> {
+        let (excludes, includes) = pathspecs
+            .iter()
+            .map(|p| Spec::parse(p))
+            .collect::<Result<Vec<Spec>, RTrimError>>()?
+            .into_iter()
+            .partition(|spec| spec.exclude);
+
+        Ok(Pathspecs { includes, excludes })
+    }
+
+    /// The pathspecs to narrow the diff down with, empty if a magic include needs matching every path.
+    pub fn for_libgit2(&self) -> Vec<&str> {
+        if self.includes.iter().all(|spec| spec.is_plain() && !spec.pattern.is_empty()) {
+            self.includes.iter().map(|spec| spec.pattern.as_str()).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Like git, only excludes match everything else.
+    pub fn matches(&self, path: &str) -> bool {
+        (self.includes.is_empty() || self.includes.iter().any(|spec| spec.matches(path)))
+            && !self.excludes.iter().any(|spec| spec.matches(path))
+    }
+}
+
+/// Resolves the relative pathspecs without magic against `prefix`, the current directory relative to the
+/// working tree, like git does. Absolute and magic pathspecs are left as they are.
+pub fn relative_to_root(pathspecs: &[String], prefix: &str) -> Result<Vec<String>, RTrimError> {
+    if prefix.is_empty() {
+        return Ok(pathspecs.to_vec());
+    }
+
+    pathspecs
+        .iter()
+        .map(|pathspec| {
+            if pathspec.starts_with(':') || Path::new(pathspec).is_absolute() {
+                return Ok(pathspec.clone());
+            }
+
+            let mut components: Vec<&str> = prefix.split('/').filter(|c| !c.is_empty()).collect();
+            for component in pathspec.split('/') {
+                match component {
+                    "" | "." => {}
+                    ".." => {
+                        components
+                            .pop()
+                            .ok_or_else(|| RTrimError::Config(format!("pathspec `{}` is outside the repository", pathspec)))?;
+                    }
+                    _ => components.push(component),
+                }
+            }
+
+            // the whole working tree
+            Ok(if components.is_empty() { String::from(".") } else { components.join("/") })
+        })
+        .collect()
+}
@@ -0,0 +1,195 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Cli;
+use crate::editorconfig;
+use crate::error::RTrimError;
+use crate::output::{display_path, Output};
+use crate::report::{plural, Report, SkipReason, SkippedFile};
+use crate::rules::Rules;
+use crate::scan::{self, Finding, StagedLines};
+use crate::trim::{TrimStats, TrimmedFile};
+use crate::fix::{format_line_numbers, print_line_endings, replace_file};
+use crate::{color, diff, filter, print_report, Outcome};
+
+/// Adds `path` or, for directories, all files below it to `files`. `.git` directories are skipped.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    if !fs::metadata(path)?.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.file_name().is_some_and(|name| name == ".git") {
+            continue;
+        }
+
+        collect_files(&entry, files)?;
+    }
+
+    Ok(())
+}
+
+/// `--files` mode: trims every line of arbitrary files, no repository involved.
+/// Errors are reported per file and don't abort the run.
+pub fn run_plain_files(cli: &Cli, out: &Output) -> Result<Outcome, RTrimError> {
+    let working_dir = std::env::current_dir()?;
+    let preview = cli.check || cli.diff;
+    let color = color::enabled(cli.color);
+    let rules = Rules::from_cli(cli);
+
+    let mut failed = 0;
+    let mut paths: Vec<PathBuf> = Vec::new();
+
+    for path in &cli.path_filters {
+        if let Err(e) = collect_files(Path::new(path), &mut paths) {
+            out.error(format_args!("{}: {}", path, e));
+            failed += 1;
+        }
+    }
+
+    let mut files = StagedLines::new();
+    let mut skipped = Vec::new();
+    let mut stats = TrimStats::default();
+    let mut progress = out.progress("trimming", paths.len());
+
+    for path in &paths {
+        progress.tick();
+
+        let file_name = path.as_path();
+        let name = file_name.to_string_lossy();
+
+        if let Some((reason, description)) = filter::skip_reason(&cli.file_filters, &name) {
+            out.verbose(format_args!("{}: skipped {}", display_path(file_name), description));
+            skipped.push(SkippedFile::new(file_name, &[], reason));
+            continue;
+        }
+
+        if cli.backup_suffix().is_some_and(|suffix| name.ends_with(suffix)) {
+            out.verbose(format_args!("{}: skipped as backup", display_path(file_name)));
+            skipped.push(SkippedFile::new(file_name, &[], SkipReason::Backup));
+            continue;
+        }
+
+        let editorconfig = cli.editorconfig.then(|| editorconfig::properties(&working_dir, file_name));
+        if editorconfig.is_some_and(|properties| properties.trim_trailing_whitespace == Some(false)) {
+            out.verbose(format_args!(
+                "{}: skipped by .editorconfig (trim_trailing_whitespace = false)",
+                display_path(file_name)
+            ));
+            skipped.push(SkippedFile::new(file_name, &[], SkipReason::EditorConfig));
+            continue;
+        }
+
+        let max_file_size = cli.max_file_size.filter(|size| *size > 0 && !cli.check);
+        if let Some(max_file_size) = max_file_size {
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            if size > max_file_size {
+                out.warning(format_args!(
+                    "{}: skipped, {} bytes exceed --max-file-size {}",
+                    display_path(file_name), size, max_file_size
+                ));
+                skipped.push(SkippedFile::new(file_name, &[], SkipReason::Size));
+                continue;
+            }
+        }
+
+        let binary = fs::read(path).is_ok_and(|content| scan::is_binary_unless_utf16(&content, rules.utf16));
+        if binary && filter::find_match(&cli.force_text, &name).is_none() {
+            out.verbose(format_args!("{}: skipped as binary, use --force-text to trim it", display_path(file_name)));
+            skipped.push(SkippedFile::new(file_name, &[], SkipReason::Binary));
+            continue;
+        }
+
+        scan::print_matching_rules(&rules, file_name, out);
+        let rules = rules.for_file(&working_dir, file_name);
+        let mut trimmed_file = match TrimmedFile::read_all(path, &rules) {
+            Ok(trimmed_file) => trimmed_file,
+            Err(e) => {
+                out.error(format_args!("{}: {}", display_path(file_name), e));
+                failed += 1;
+                continue;
+            }
+        };
+
+        if let Some(eol) = rules.eol {
+            trimmed_file.convert_line_endings(eol);
+        }
+        if !trimmed_file.is_modified() {
+            continue;
+        }
+
+        let trimmed_lines = trimmed_file.trimmed_lines().to_vec();
+
+        let last_line = trimmed_file.line_count() as u32;
+        let mut findings: Vec<Finding> = trimmed_lines
+            .iter()
+            .filter(|&&line_no| !trimmed_file.is_removed(line_no))
+            .filter(|&&line_no| trimmed_file.new_line(line_no) != trimmed_file.original_line(line_no))
+            .flat_map(|&line_no| scan::line_findings(line_no, trimmed_file.original_line(line_no), &rules))
+            .chain(trimmed_file.removed_lines().iter().copied().map(Finding::extra_blank_line))
+            .chain(trimmed_file.removed_from().map(Finding::blank_at_eof))
+            .collect();
+        if trimmed_file.adds_final_newline() {
+            findings.push(Finding::missing_final_newline(last_line, trimmed_file.original_line(last_line)));
+        }
+        if let Some(line_no) = (1..=last_line).find(|l| trimmed_file.converts_line_ending(*l)) {
+            findings.push(Finding::wrong_line_ending(line_no, trimmed_file.original_line(line_no)));
+        }
+        findings.sort_by_key(|f| f.line);
+
+        if cli.diff {
+            let diff = diff::unified_diff(file_name, &trimmed_file, color);
+            if !diff.is_empty() {
+                out.report_bytes(&diff);
+            }
+        } else if !preview {
+            match replace_file(path, &trimmed_file, cli.backup_suffix()) {
+                Ok(Some(backup)) => {
+                    out.verbose(format_args!("{}: backed up to {}", display_path(file_name), backup.display()))
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    out.error(format_args!("{}: {}", display_path(file_name), e));
+                    failed += 1;
+                    continue;
+                }
+            }
+
+            if !trimmed_lines.is_empty() {
+                out.verbose(format_args!(
+                    "{}: trimmed {} ({})",
+                    display_path(file_name),
+                    plural(trimmed_lines.len(), "line"),
+                    format_line_numbers(&trimmed_lines)
+                ));
+            }
+            print_line_endings(file_name, &trimmed_file, out);
+
+            stats.record(file_name, &trimmed_file);
+        }
+
+        files.insert(path.clone(), findings);
+    }
+
+    drop(progress);
+
+    let report = Report::new(&working_dir, paths.len(), &files, &stats, skipped);
+
+    print_report(cli, &report, color, out)?;
+
+    if failed > 0 {
+        Err(RTrimError::Failed(failed))
+    } else if preview && !files.is_empty() {
+        Ok(Outcome::Found)
+    } else if stats.files_modified() > 0 {
+        Ok(Outcome::Modified)
+    } else {
+        Ok(Outcome::Clean)
+    }
+}
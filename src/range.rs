@@ -0,0 +1,248 @@
+use git2::{Commit, DiffLineType, Oid, Patch, Repository, Revwalk};
+use std::io::{self, BufRead};
+
+use crate::color::{self, CYAN, YELLOW};
+use crate::error::RTrimError;
+use crate::output::Output;
+use crate::report::plural;
+use crate::rules::Rules;
+use crate::scan::Finding;
+use crate::Outcome;
+
+/// A line with trailing whitespaces added by a commit.
+pub struct CommitFinding {
+    pub commit: String,
+    pub path: String,
+    pub finding: Finding,
+}
+
+/// The added lines with trailing whitespaces of a commit compared to its first parent,
+/// or to the empty tree for a root commit.
+fn commit_findings(repo: &Repository, commit: &Commit) -> Result<Vec<CommitFinding>, RTrimError> {
+    let mut findings: Vec<CommitFinding> = Vec::new();
+
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?.tree()?),
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    // history is checked with the default rules, the configuration may have changed since
+    let rules = Rules::default();
+    let short_id = commit.as_object().short_id()?.as_str().unwrap_or_default().to_string();
+
+    for delta_index in 0..diff.deltas().len() {
+        // binary files have no patch
+        let patch = match Patch::from_diff(&diff, delta_index)? {
+            Some(patch) => patch,
+            None => continue,
+        };
+
+        let path = match patch.delta().new_file().path() {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => continue,
+        };
+
+        for hunk_index in 0..patch.num_hunks() {
+            for line_index in 0..patch.num_lines_in_hunk(hunk_index)? {
+                let diff_line = patch.line_in_hunk(hunk_index, line_index)?;
+
+                if diff_line.origin_value() != DiffLineType::Addition {
+                    continue;
+                }
+
+                if let Some(line_no) = diff_line.new_lineno() {
+                    let line = diff_line.content();
+                    if rules.has_trailing_whitespace(line) {
+                        findings.push(CommitFinding {
+                            commit: short_id.clone(),
+                            path: path.clone(),
+                            finding: Finding::new(line_no, line, &rules),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+/// Collects the added lines with trailing whitespaces of every commit in `revwalk`.
+/// Each commit is diffed against its first parent, merge commits are skipped because their changes
+/// were already checked in the merged commits.
+pub fn check_commits(repo: &Repository, revwalk: Revwalk, out: &Output) -> Result<(Vec<CommitFinding>, usize), RTrimError> {
+    let oids = revwalk.collect::<Result<Vec<Oid>, git2::Error>>()?;
+    let mut findings: Vec<CommitFinding> = Vec::new();
+    let mut commits_checked = 0;
+    let mut progress = out.progress("checking", oids.len());
+
+    for oid in oids {
+        progress.tick();
+
+        let commit = repo.find_commit(oid)?;
+        if commit.parent_count() > 1 {
+            log::debug!("{} is a merge commit, skipped", oid);
+            continue;
+        }
+
+        commits_checked += 1;
+        findings.extend(commit_findings(repo, &commit)?);
+    }
+
+    Ok((findings, commits_checked))
+}
+
+/// Prints one `sha path:line:column: content` entry per finding followed by a summary.
+pub fn print_findings(findings: &[CommitFinding], commits_checked: usize, color: bool, out: &Output) -> Outcome {
+    let rules = Rules::default();
+
+    for f in findings {
+        out.report(format!(
+            "{} {}:{}:{}: {}",
+            color::paint(&f.commit, YELLOW, color),
+            color::paint(&f.path, CYAN, color),
+            f.finding.line,
+            f.finding.column,
+            color::highlight_whitespaces(&f.finding.content, &rules, color)
+        ));
+    }
+
+    let checked = plural(commits_checked, "commit");
+
+    if findings.is_empty() {
+        out.info(format!("rtrim: {} checked, no trailing whitespaces found", checked));
+        Outcome::Clean
+    } else {
+        let mut commits: Vec<&str> = findings.iter().map(|f| f.commit.as_str()).collect();
+        commits.dedup();
+
+        out.info(format!(
+            "rtrim: {} checked, {} with trailing whitespaces, {} found",
+            checked,
+            plural(commits.len(), "commit"),
+            plural(findings.len(), "line")
+        ));
+        Outcome::Found
+    }
+}
+
+/// Checks the commits of a revision range like `origin/main..HEAD` without touching the index or working tree.
+pub fn run_check_range(range: &str, color: bool, out: &Output) -> Result<Outcome, RTrimError> {
+    let repo = Repository::discover(std::env::current_dir()?)?;
+
+    let mut revwalk = repo.revwalk()?;
+    let pushed = if range.contains("..") {
+        revwalk.push_range(range)
+    } else {
+        repo.revparse_single(range).and_then(|object| revwalk.push(object.id()))
+    };
+    pushed.map_err(|e| RTrimError::Config(format!("invalid revision range '{}': {}", range, e.message())))?;
+
+    let (findings, commits_checked) = check_commits(&repo, revwalk, out)?;
+
+    Ok(print_findings(&findings, commits_checked, color, out))
+}
+
+/// Checks the commits about to be pushed, reads the `<local ref> <local sha> <remote ref> <remote sha>`
+/// lines git passes a pre-push hook on stdin.
+pub fn run_pre_push(remote: Option<&str>, color: bool, out: &Output) -> Result<Outcome, RTrimError> {
+    let repo = Repository::discover(std::env::current_dir()?)?;
+
+    // commits on none of the remote's branches are new to it
+    let remote_refs = match remote {
+        Some(remote) => format!("refs/remotes/{}/*", remote),
+        None => String::from("refs/remotes/*"),
+    };
+
+    let mut revwalk = repo.revwalk()?;
+    let mut pushed_any = false;
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let (local_ref, local_sha, remote_sha) = match fields[..] {
+            [] => continue,
+            [local_ref, local_sha, _, remote_sha] => (local_ref, local_sha, remote_sha),
+            _ => return Err(RTrimError::Config(format!("unexpected pre-push input '{}'", line))),
+        };
+
+        let local = Oid::from_str(local_sha)?;
+        if local.is_zero() {
+            log::debug!("{} is deleted, nothing to check", local_ref);
+            continue;
+        }
+
+        revwalk.push(local)?;
+        pushed_any = true;
+
+        let remote = Oid::from_str(remote_sha)?;
+        if remote.is_zero() || revwalk.hide(remote).is_err() {
+            log::debug!("{} is new to the remote, checking the commits not in {}", local_ref, remote_refs);
+            revwalk.hide_glob(&remote_refs)?;
+        }
+    }
+
+    if !pushed_any {
+        out.info("rtrim: nothing to push");
+        return Ok(Outcome::Clean);
+    }
+
+    let (findings, commits_checked) = check_commits(&repo, revwalk, out)?;
+
+    Ok(print_findings(&findings, commits_checked, color, out))
+}
+
+/// Checks single commits against their first parent, merge commits included. Only reads the object
+/// database, so it works in bare repositories. The findings are grouped per commit.
+pub fn run_check_commit(revs: &[String], color: bool, out: &Output) -> Result<Outcome, RTrimError> {
+    let repo = Repository::discover(std::env::current_dir()?)?;
+    let rules = Rules::default();
+    let mut findings_total = 0;
+    let mut commits_found = 0;
+
+    for rev in revs {
+        let commit = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| RTrimError::Config(format!("invalid revision '{}': {}", rev, e.message())))?;
+
+        let findings = commit_findings(&repo, &commit)?;
+        if findings.is_empty() {
+            continue;
+        }
+
+        out.report(format!(
+            "{} {}",
+            color::paint(&findings[0].commit, YELLOW, color),
+            commit.summary().unwrap_or_default()
+        ));
+        for f in &findings {
+            out.report(format!(
+                "    {}:{}:{}: {}",
+                color::paint(&f.path, CYAN, color),
+                f.finding.line,
+                f.finding.column,
+                color::highlight_whitespaces(&f.finding.content, &rules, color)
+            ));
+        }
+
+        findings_total += findings.len();
+        commits_found += 1;
+    }
+
+    let checked = plural(revs.len(), "commit");
+
+    if findings_total == 0 {
+        out.info(format!("rtrim: {} checked, no trailing whitespaces found", checked));
+        Ok(Outcome::Clean)
+    } else {
+        out.info(format!(
+            "rtrim: {} checked, {} with trailing whitespaces, {} found",
+            checked,
+            plural(commits_found, "commit"),
+            plural(findings_total, "line")
+        ));
+        Ok(Outcome::Found)
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::trim::split_line_ending;
+
+/// How the protected regions of a file are found, the trailing whitespaces inside them are kept.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Region {
+    /// The lines between ``` or ~~~ fences, like Markdown code blocks.
+    CodeFences,
+    /// The content of YAML literal and folded block scalars.
+    BlockScalars,
+    /// Nothing is protected, turns a default off.
+    #[serde(rename = "none")]
+    Off,
+}
+
+/// The regions of `--protect-regions` per extension, the `regions` table of the config file adds to them.
+pub fn default_regions() -> BTreeMap<String, Region> {
+    [
+        ("md", Region::CodeFences),
+        ("markdown", Region::CodeFences),
+        ("yaml", Region::BlockScalars),
+        ("yml", Region::BlockScalars),
+    ]
+    .into_iter()
+    .map(|(extension, region)| (extension.to_string(), region))
+    .collect()
+}
+
+/// The number of leading spaces.
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// The fence character and length if `line` opens or closes a code block, at most 3 spaces indented.
+fn fence(line: &str) -> Option<(char, usize)> {
+    if indentation(line) > 3 {
+        return None;
+    }
+
+    let line = line.trim_start_matches(' ');
+    let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = line.len() - line.trim_start_matches(c).len();
+
+    (length >= 3).then_some((c, length))
+}
+
+/// Whether a YAML line ends with a block scalar header like `key: |`, `- >-` or `key: |2 # comment`.
+fn starts_block_scalar(line: &str) -> bool {
+    let line = match line.find(" #") {
+        Some(comment) => &line[..comment],
+        None => line,
+    };
+    let mut tokens = line.split_whitespace().rev();
+
+    let Some(header) = tokens.next() else {
+        return false;
+    };
+    let mut indicators = header.chars();
+    if !matches!(indicators.next(), Some('|' | '>')) {
+        return false;
+    }
+    let rest: String = indicators.collect();
+    let valid_header = rest.len() <= 2 && rest.chars().all(|c| c == '+' || c == '-' || c.is_ascii_digit());
+
+    valid_header && tokens.next().is_none_or(|token| token.ends_with(':') || token == "-" || token == "---")
+}
+
+/// The lines (1-based, ascending) of `content` inside the protected regions.
+pub fn protected_lines(region: Region, content: &[u8]) -> Vec<u32> {
+    let mut protected: Vec<u32> = Vec::new();
+
+    match region {
+        Region::CodeFences => {
+            let mut open: Option<(char, usize)> = None;
+
+            for (line_no, line) in (1..).zip(content.split_inclusive(|b| *b == b'\n')) {
+                let line = String::from_utf8_lossy(split_line_ending(line).0);
+
+                match (open, fence(&line)) {
+                    (None, Some(opening)) => open = Some(opening),
+                    // the closing fence is at least as long and has no info string
+                    (Some((c, length)), Some((closing, closing_length)))
+                        if closing == c && closing_length >= length && line.trim().chars().all(|x| x == c) =>
+                    {
+                        open = None
+                    }
+                    (Some(_), _) => protected.push(line_no),
+                    (None, None) => {}
+                }
+            }
+        }
+        Region::BlockScalars => {
+            // the indentation of the line with the header of the current block scalar
+            let mut parent: Option<usize> = None;
+
+            for (line_no, line) in (1..).zip(content.split_inclusive(|b| *b == b'\n')) {
+                let line = String::from_utf8_lossy(split_line_ending(line).0);
+                let blank = line.trim().is_empty();
+
+                if let Some(parent_indentation) = parent {
+                    if blank || indentation(&line) > parent_indentation {
+                        protected.push(line_no);
+                        continue;
+                    }
+                    parent = None;
+                }
+
+                if starts_block_scalar(&line) {
+                    parent = Some(indentation(&line));
+                }
+            }
+        }
+        Region::Off => {}
+    }
+
+    protected
+}
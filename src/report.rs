@@ -0,0 +1,590 @@
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Severity;
+use crate::color::{self, CYAN};
+use crate::output::display_path;
+use crate::rules::Rules;
+use crate::scan::{Finding, FindingKind, StagedLines};
+use crate::trim::TrimStats;
+
+/// The SARIF rules, in the order of their `ruleIndex`.
+const RULES: [FindingKind; 10] = [
+    FindingKind::TrailingWhitespace,
+    FindingKind::BlankAtEof,
+    FindingKind::MissingFinalNewline,
+    FindingKind::SpaceBeforeTab,
+    FindingKind::TabInIndent,
+    FindingKind::IndentWithNonTab,
+    FindingKind::LineEnding,
+    FindingKind::MixedLineEnding,
+    FindingKind::InvisibleCharacter,
+    FindingKind::ExtraBlankLine,
+];
+
+/// What a finding reports, or what was done about it outside of check mode.
+fn finding_message(kind: FindingKind, check: bool) -> String {
+    match (kind, check) {
+        (_, true) => kind.description().to_string(),
+        (FindingKind::TrailingWhitespace, false) => format!("{} trimmed", kind.description()),
+        (FindingKind::BlankAtEof | FindingKind::ExtraBlankLine, false) => format!("{} removed", kind.description()),
+        (FindingKind::MissingFinalNewline, false) => String::from("final newline added"),
+        (FindingKind::SpaceBeforeTab, false) => format!("{} fixed", kind.description()),
+        // only converted with --fix-tabs, --fix-spaces, --eol and --fix-invisible
+        (
+            FindingKind::TabInIndent
+            | FindingKind::IndentWithNonTab
+            | FindingKind::MixedLineEnding
+            | FindingKind::InvisibleCharacter,
+            false,
+        ) => kind.description().to_string(),
+        (FindingKind::LineEnding, false) => String::from("line endings converted"),
+    }
+}
+
+/// The lines of `findings`, once each.
+fn line_numbers(findings: &[Finding]) -> Vec<u32> {
+    let mut lines: Vec<u32> = findings.iter().map(|f| f.line).collect();
+    lines.dedup();
+    lines
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// `1 file`, `2 files`
+pub fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Escapes a workflow command property value, see
+/// https://github.com/actions/toolkit/blob/main/packages/core/src/command.ts
+fn escape_github_property(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Escapes text for use in XML attribute values.
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push_str(&format!("&#{};", c as u32)),
+            c if (c as u32) < 0x20 => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Percent-encodes a repo-relative path for use as a URI reference, keeping `/` separators.
+fn encode_uri_path(path: &[u8]) -> String {
+    let mut encoded = String::with_capacity(path.len());
+
+    for &byte in path {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// Everything a run found and did, independent of the output format.
+#[derive(Serialize)]
+pub struct Report {
+    pub workdir: String,
+    pub files_scanned: usize,
+    pub files_modified: usize,
+    pub lines_trimmed: usize,
+    pub bytes_removed: usize,
+    pub line_endings_converted: usize,
+    pub files: Vec<FileReport>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Serialize)]
+pub struct FileReport {
+    pub path: String,
+    /// The repository path, `path` is it with invalid UTF-8 replaced. Only the text output quotes it.
+    #[serde(skip)]
+    pub file_name: PathBuf,
+    pub lines: Vec<u32>,
+    pub findings: Vec<Finding>,
+    pub modified: bool,
+    pub line_endings_converted: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    Excluded,
+    Extension,
+    Backup,
+    Size,
+    Binary,
+    Protected,
+    Patch,
+    #[serde(rename = "editorconfig")]
+    EditorConfig,
+    /// Not valid UTF-16 despite its BOM.
+    Encoding,
+    /// The working tree file has changes that aren't staged.
+    Unstaged,
+    /// An unresolved conflict or leftover conflict markers during a merge.
+    Conflict,
+    /// Staged, but not in the working tree, deleted since or left out by a sparse checkout.
+    Missing,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::Excluded => "by exclude",
+            SkipReason::Extension => "by extension",
+            SkipReason::Backup => "as backup",
+            SkipReason::Size => "by size",
+            SkipReason::Binary => "as binary",
+            SkipReason::Protected => "(protected region)",
+            SkipReason::Patch => "(patch file)",
+            SkipReason::EditorConfig => "by .editorconfig",
+            SkipReason::Encoding => "by encoding",
+            SkipReason::Unstaged => "with unstaged changes",
+            SkipReason::Conflict => "with conflicts",
+            SkipReason::Missing => "as missing",
+        }
+    }
+}
+
+/// A file with findings that was left alone.
+#[derive(Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    /// The repository path, `path` is it with invalid UTF-8 replaced. Only the text output quotes it.
+    #[serde(skip)]
+    pub file_name: PathBuf,
+    pub lines: Vec<u32>,
+    pub reason: SkipReason,
+}
+
+impl SkippedFile {
+    pub fn new(file_name: &Path, findings: &[Finding], reason: SkipReason) -> Self {
+        SkippedFile {
+            path: file_name.to_string_lossy().into_owned(),
+            file_name: file_name.to_path_buf(),
+            lines: line_numbers(findings),
+            reason,
+        }
+    }
+}
+
+impl Report {
+    /// `stats` is empty in check mode.
+    pub fn new(
+        workdir: &Path,
+        files_scanned: usize,
+        files: &StagedLines,
+        stats: &TrimStats,
+        skipped: Vec<SkippedFile>,
+    ) -> Self {
+        let files: Vec<FileReport> = files
+            .iter()
+            .map(|(file_name, findings)| FileReport {
+                path: file_name.to_string_lossy().into_owned(),
+                file_name: file_name.clone(),
+                lines: line_numbers(findings),
+                findings: findings.clone(),
+                modified: stats.files.contains_key(file_name),
+                line_endings_converted: stats.line_endings.get(file_name).copied().unwrap_or(0),
+            })
+            .collect();
+
+        Report {
+            workdir: workdir.to_string_lossy().into_owned(),
+            files_scanned,
+            files_modified: stats.files_modified(),
+            lines_trimmed: stats.lines_trimmed(),
+            bytes_removed: stats.bytes_removed,
+            line_endings_converted: stats.line_endings.values().sum(),
+            files,
+            skipped,
+        }
+    }
+
+    /// One `path:line:column: content` entry per line with trailing whitespaces.
+    pub fn to_text(&self, color: bool, rules: &Rules) -> String {
+        let mut lines: Vec<String> = Vec::new();
+
+        for file in &self.files {
+            let rules = rules.for_file(Path::new(&self.workdir), &file.file_name);
+            for finding in &file.findings {
+                let message = match finding.kind {
+                    FindingKind::TrailingWhitespace => {
+                        let character = finding.character.as_ref().map(|c| format!(" ({})", c)).unwrap_or_default();
+                        format!("{}{}", color::highlight_whitespaces(&finding.content, &rules, color), character)
+                    }
+                    FindingKind::BlankAtEof
+                    | FindingKind::ExtraBlankLine
+                    | FindingKind::MissingFinalNewline
+                    | FindingKind::SpaceBeforeTab
+                    | FindingKind::TabInIndent
+                    | FindingKind::LineEnding => finding.kind.description().to_string(),
+                    FindingKind::IndentWithNonTab => {
+                        let spaces = (finding.end_column - finding.column) as usize;
+                        format!("{} ({})", finding.kind.description(), plural(spaces, "space"))
+                    }
+                    FindingKind::MixedLineEnding => {
+                        format!("{} ({})", finding.kind.description(), finding.line_ending.unwrap_or_default())
+                    }
+                    FindingKind::InvisibleCharacter => {
+                        format!("{} ({})", finding.kind.description(), finding.character.as_deref().unwrap_or_default())
+                    }
+                };
+                lines.push(format!(
+                    "{}:{}:{}: {}",
+                    color::paint(&display_path(&file.file_name), CYAN, color),
+                    finding.line,
+                    finding.column,
+                    message
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// A one-line summary, distinguishes between nothing staged and clean staged files.
+    pub fn summary(&self, check: bool) -> String {
+        format!("rtrim: {}", self.summary_text(check))
+    }
+
+    /// Like `summary` for one of several repositories.
+    pub fn repository_summary(&self, check: bool, repository: &str) -> String {
+        format!("rtrim: {}: {}", repository, self.summary_text(check))
+    }
+
+    fn summary_text(&self, check: bool) -> String {
+        if self.files_scanned == 0 && self.skipped.is_empty() {
+            return String::from("no staged files matched");
+        }
+
+        let mut summary = self.findings_summary(check);
+
+        let mut reasons: BTreeMap<SkipReason, usize> = BTreeMap::new();
+        for skipped in &self.skipped {
+            *reasons.entry(skipped.reason).or_default() += 1;
+        }
+
+        for (reason, count) in reasons {
+            summary.push_str(&format!(", {} skipped {}", plural(count, "file"), reason.as_str()));
+        }
+
+        summary
+    }
+
+    fn findings_summary(&self, check: bool) -> String {
+        let scanned = plural(self.files_scanned, "file");
+
+        if self.files.is_empty() {
+            return format!("{} scanned, no trailing whitespaces found", scanned);
+        }
+
+        if check {
+            let lines_found: usize = self.files.iter().map(|f| f.lines.len()).sum();
+
+            format!(
+                "{} scanned, {} with trailing whitespaces, {} found",
+                scanned,
+                plural(self.files.len(), "file"),
+                plural(lines_found, "line")
+            )
+        } else {
+            let mut summary = format!(
+                "{} scanned, {} modified, {} trimmed",
+                scanned,
+                plural(self.files_modified, "file"),
+                plural(self.lines_trimmed, "line")
+            );
+            if self.line_endings_converted > 0 {
+                summary.push_str(&format!(", {} converted", plural(self.line_endings_converted, "line ending")));
+            }
+            summary
+        }
+    }
+
+    /// One path per modified file, in check mode per file with findings.
+    pub fn to_file_list(&self, check: bool, null_terminated: bool) -> String {
+        let terminator = if null_terminated { '\0' } else { '\n' };
+
+        self.files
+            .iter()
+            .filter(|file| check || file.modified)
+            .map(|file| format!("{}{}", file.path, terminator))
+            .collect()
+    }
+
+    /// A `git diff --stat` like table of the lines per file with a total line, at most `width` columns wide.
+    /// Paths that don't fit are truncated from the left.
+    pub fn to_stat(&self, check: bool, width: usize) -> String {
+        let files: Vec<&FileReport> = self.files.iter().filter(|file| check || file.modified).collect();
+        let names: Vec<String> = files.iter().map(|file| display_path(&file.file_name)).collect();
+        let Some(max_count) = files.iter().map(|file| file.lines.len()).max() else {
+            return String::new();
+        };
+
+        let count_width = max_count.to_string().len();
+        let name_width = names
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(width / 2);
+        // " name | count bar"
+        let bar_width = width.saturating_sub(name_width + count_width + 5).max(1);
+
+        let mut lines: Vec<String> = Vec::new();
+        let mut total = 0;
+
+        for (file, name) in files.iter().zip(&names) {
+            let count = file.lines.len();
+            total += count;
+
+            let length = name.chars().count();
+            let name = if length > name_width {
+                let kept: String = name.chars().skip(length - name_width + 3).collect();
+                format!("...{}", kept)
+            } else {
+                name.clone()
+            };
+            let bar = if max_count > bar_width { (count * bar_width).div_ceil(max_count) } else { count };
+
+            lines.push(format!(
+                " {:<name_width$} | {:>count_width$} {}",
+                name,
+                count,
+                "-".repeat(bar),
+                name_width = name_width,
+                count_width = count_width
+            ));
+        }
+
+        let action = if check { "with trailing whitespaces" } else { "trimmed" };
+        lines.push(format!(" {}, {} {}", plural(files.len(), "file"), plural(total, "line"), action));
+
+        lines.join("\n")
+    }
+
+    /// GitHub Actions workflow commands, errors in check mode and notices for trimmed lines.
+    pub fn to_github(&self, check: bool) -> String {
+        let command = if check { "error" } else { "notice" };
+
+        let mut lines: Vec<String> = Vec::new();
+
+        for file in &self.files {
+            for finding in &file.findings {
+                let message = finding_message(finding.kind, check);
+                lines.push(format!(
+                    "::{} file={},line={},col={}::{}",
+                    command,
+                    escape_github_property(&file.path),
+                    finding.line,
+                    finding.column,
+                    message
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// A SARIF 2.1.0 log with one result per finding, errors in check mode and notes for trimmed lines.
+    pub fn to_sarif(&self, check: bool) -> Result<String, serde_json::Error> {
+        let level = if check { "error" } else { "note" };
+
+        let results: Vec<serde_json::Value> = self
+            .files
+            .iter()
+            .flat_map(|file| {
+                file.findings.iter().map(move |finding| {
+                    json!({
+                        "ruleId": finding.kind.rule_id(),
+                        "ruleIndex": RULES.iter().position(|kind| *kind == finding.kind),
+                        "level": level,
+                        "message": { "text": capitalize(&finding_message(finding.kind, check)) },
+                        "locations": [{
+                            "physicalLocation": {
+                                "artifactLocation": {
+                                    "uri": encode_uri_path(file.file_name.as_os_str().as_encoded_bytes()),
+                                    "uriBaseId": "SRCROOT"
+                                },
+                                "region": {
+                                    "startLine": finding.line,
+                                    "startColumn": finding.column,
+                                    "endColumn": finding.end_column
+                                }
+                            }
+                        }]
+                    })
+                })
+            })
+            .collect();
+
+        let base_uri = format!("file://{}/", encode_uri_path(self.workdir.trim_end_matches('/').as_bytes()));
+        let log = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": RULES.iter().map(|kind| json!({
+                            "id": kind.rule_id(),
+                            "name": match kind {
+                                FindingKind::TrailingWhitespace => "TrailingWhitespace",
+                                FindingKind::BlankAtEof => "BlankAtEof",
+                                FindingKind::MissingFinalNewline => "MissingFinalNewline",
+                                FindingKind::SpaceBeforeTab => "SpaceBeforeTab",
+                                FindingKind::TabInIndent => "TabInIndent",
+                                FindingKind::IndentWithNonTab => "IndentWithNonTab",
+                                FindingKind::LineEnding => "LineEnding",
+                                FindingKind::MixedLineEnding => "MixedLineEnding",
+                                FindingKind::InvisibleCharacter => "InvisibleCharacter",
+                                FindingKind::ExtraBlankLine => "ExtraBlankLine",
+                            },
+                            "shortDescription": { "text": match kind {
+                                FindingKind::TrailingWhitespace => "Line ends with whitespaces",
+                                FindingKind::BlankAtEof => "File ends with blank lines",
+                                FindingKind::MissingFinalNewline => "Last line has no line ending",
+                                FindingKind::SpaceBeforeTab => "Indentation has spaces before a tab",
+                                FindingKind::TabInIndent => "Indentation has tabs",
+                                FindingKind::IndentWithNonTab => "Indentation has spaces instead of tabs",
+                                FindingKind::LineEnding => "Line ending differs from the configured one",
+                                FindingKind::MixedLineEnding => "Line ending differs from the rest of the file",
+                                FindingKind::InvisibleCharacter => "Line ends with an invisible character",
+                                FindingKind::ExtraBlankLine => "Run of blank lines is too long",
+                            } },
+                            "defaultConfiguration": { "level": "error" }
+                        })).collect::<Vec<_>>()
+                    }
+                },
+                "originalUriBaseIds": {
+                    "SRCROOT": { "uri": base_uri }
+                },
+                "results": results
+            }]
+        });
+
+        serde_json::to_string_pretty(&log)
+    }
+
+    /// A checkstyle XML document with one `<file>` element per file with findings.
+    pub fn to_checkstyle(&self, check: bool) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n");
+
+        for file in &self.files {
+            xml.push_str(&format!("  <file name=\"{}\">\n", escape_xml(&file.path)));
+
+            for finding in &file.findings {
+                xml.push_str(&format!(
+                    "    <error line=\"{}\" column=\"{}\" severity=\"warning\" message=\"{}\" source=\"rtrim.{}\"/>\n",
+                    finding.line,
+                    finding.column,
+                    capitalize(&finding_message(finding.kind, check)),
+                    finding.kind.rule_id()
+                ));
+            }
+
+            xml.push_str("  </file>\n");
+        }
+
+        xml.push_str("</checkstyle>");
+        xml
+    }
+
+    /// A reviewdog diagnostic result, in check mode with a suggestion removing the whitespaces.
+    /// reviewdog counts columns in UTF-8 bytes.
+    pub fn to_rdjson(&self, check: bool, severity: Severity, rules: &Rules) -> Result<String, serde_json::Error> {
+        let diagnostics: Vec<serde_json::Value> = self
+            .files
+            .iter()
+            .flat_map(|file| {
+                let rules = rules.for_file(Path::new(&self.workdir), &file.file_name);
+                file.findings.iter().map(move |finding| {
+                    // the indentation is ASCII, so its columns are bytes too
+                    let (start, end) = match finding.kind {
+                        // the whitespaces the rules keep, like a Markdown hard line break, aren't part of it
+                        FindingKind::TrailingWhitespace => {
+                            (rules.trim(finding.content.as_bytes()).len(), finding.content.len())
+                        }
+                        FindingKind::BlankAtEof
+                        | FindingKind::MissingFinalNewline
+                        | FindingKind::LineEnding
+                        | FindingKind::MixedLineEnding
+                        | FindingKind::ExtraBlankLine => {
+                            (finding.content.len(), finding.content.len())
+                        }
+                        FindingKind::SpaceBeforeTab | FindingKind::TabInIndent | FindingKind::IndentWithNonTab => {
+                            (finding.column as usize - 1, finding.end_column as usize - 1)
+                        }
+                        // the text before it needn't be ASCII, so the byte columns are counted again
+                        FindingKind::InvisibleCharacter => {
+                            let start = finding.content.char_indices().nth(finding.column as usize - 1);
+                            let (start, c) = start.unwrap_or((finding.content.len(), ' '));
+                            (start, start + c.len_utf8())
+                        }
+                    };
+                    let range = json!({
+                        "start": { "line": finding.line, "column": start + 1 },
+                        "end": { "line": finding.line, "column": end + 1 }
+                    });
+
+                    let mut diagnostic = json!({
+                        "message": finding_message(finding.kind, check),
+                        "location": { "path": file.path, "range": range },
+                        "severity": severity.as_rdjson(),
+                        "code": { "value": finding.kind.rule_id() }
+                    });
+                    // removing blank lines spans several lines, only the whitespaces are suggested
+                    if check && finding.kind == FindingKind::TrailingWhitespace && start < end {
+                        diagnostic["suggestions"] = json!([{ "range": range, "text": "" }]);
+                    }
+
+                    diagnostic
+                })
+            })
+            .collect();
+
+        let result = json!({
+            "source": { "name": env!("CARGO_PKG_NAME") },
+            "severity": severity.as_rdjson(),
+            "diagnostics": diagnostics
+        });
+
+        serde_json::to_string_pretty(&result)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
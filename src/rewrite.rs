@@ -0,0 +1,209 @@
+use git2::build::{CheckoutBuilder, TreeUpdateBuilder};
+use git2::{BranchType, Commit, DiffLineType, FileMode, Oid, Patch, Repository, RevparseMode, Sort, Tree};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::cli::FileFilters;
+use crate::error::RTrimError;
+use crate::fix_head::ensure_clean;
+use crate::output::Output;
+use crate::report::plural;
+use crate::rules::Rules;
+use crate::trim::trim_lines;
+use crate::{filter, Outcome};
+
+/// The lines with trailing whitespaces `tree` added compared to `base`, per path.
+fn added_lines(
+    repo: &Repository,
+    base: &Tree,
+    tree: &Tree,
+    file_filters: &FileFilters,
+) -> Result<BTreeMap<String, Vec<u32>>, RTrimError> {
+    let diff = repo.diff_tree_to_tree(Some(base), Some(tree), None)?;
+    let rules = Rules::default();
+    let mut files: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+
+    for delta_index in 0..diff.deltas().len() {
+        // binary files have no patch
+        let Some(patch) = Patch::from_diff(&diff, delta_index)? else {
+            continue;
+        };
+        let Some(path) = patch.delta().new_file().path().map(|p| p.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        if filter::skip_reason(file_filters, &path).is_some() {
+            continue;
+        }
+
+        for hunk_index in 0..patch.num_hunks() {
+            for line_index in 0..patch.num_lines_in_hunk(hunk_index)? {
+                let diff_line = patch.line_in_hunk(hunk_index, line_index)?;
+
+                if diff_line.origin_value() != DiffLineType::Addition {
+                    continue;
+                }
+
+                if let Some(line_no) = diff_line.new_lineno() {
+                    let line = diff_line.content();
+                    if rules.has_trailing_whitespace(line) {
+                        files.entry(path.clone()).or_default().push(line_no);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// `tree` with the given lines trimmed, the mode of each file is kept.
+fn trimmed_tree(repo: &Repository, tree: &Tree, files: &BTreeMap<String, Vec<u32>>) -> Result<Oid, RTrimError> {
+    if files.is_empty() {
+        return Ok(tree.id());
+    }
+
+    let mut builder = TreeUpdateBuilder::new();
+
+    for (path, lines) in files {
+        let entry = tree.get_path(std::path::Path::new(path))?;
+        let blob = repo.find_blob(entry.id())?;
+        let mode = if entry.filemode() == i32::from(FileMode::BlobExecutable) {
+            FileMode::BlobExecutable
+        } else {
+            FileMode::Blob
+        };
+
+        builder.upsert(path.as_str(), repo.blob(&trim_lines(blob.content(), lines, &Rules::default()))?, mode);
+    }
+
+    Ok(builder.create_updated(repo, tree)?)
+}
+
+/// The local branch `name` refers to, `HEAD` is resolved to the checked out branch.
+fn branch_name(repo: &Repository, name: &str) -> Option<String> {
+    let reference = repo.resolve_reference_from_short_name(name).ok()?;
+    let reference = if reference.is_branch() {
+        reference
+    } else {
+        reference.resolve().ok().filter(|r| r.is_branch())?
+    };
+
+    reference.shorthand().map(String::from)
+}
+
+/// `rewrite` subcommand: replays the commits of `range` with the trailing whitespaces they added removed.
+/// Every line added since the start of the range is trimmed, so a trim carries over to the later commits.
+/// Messages, authors and committers are kept, commits only get new ids if their tree or a parent changed.
+/// The result goes to a new branch unless `force` moves the branch of the range's tip.
+pub fn run_rewrite(
+    range: &str,
+    branch: Option<&str>,
+    force: bool,
+    file_filters: &FileFilters,
+    out: &Output,
+) -> Result<Outcome, RTrimError> {
+    let repo = Repository::discover(std::env::current_dir()?)?;
+
+    let revspec = repo
+        .revparse(range)
+        .map_err(|e| RTrimError::Config(format!("invalid revision range '{}': {}", range, e.message())))?;
+    let (Some(from), Some(to)) = (revspec.from(), revspec.to()) else {
+        return Err(RTrimError::Config(format!("'{}' is not a range like main..feature", range)));
+    };
+    if !revspec.mode().contains(RevparseMode::RANGE) {
+        return Err(RTrimError::Config(format!("'{}' is not a range like main..feature", range)));
+    }
+
+    let tip = range.split_once("..").map_or(range, |(_, tip)| tip.trim_start_matches('.'));
+    let tip = if tip.is_empty() { "HEAD" } else { tip };
+    let tip_branch = branch_name(&repo, tip);
+
+    let target = match (force, branch) {
+        (true, _) => tip_branch
+            .clone()
+            .ok_or_else(|| RTrimError::Config(format!("--force needs '{}' to be a local branch", tip)))?,
+        (false, Some(branch)) => branch.to_string(),
+        (false, None) => tip_branch
+            .as_ref()
+            .map(|name| format!("{}-trimmed", name))
+            .ok_or_else(|| RTrimError::Config(format!("'{}' is not a branch, name the new one with --branch", tip)))?,
+    };
+    if !force && repo.find_branch(&target, BranchType::Local).is_ok() {
+        return Err(RTrimError::Config(format!("branch '{}' already exists", target)));
+    }
+
+    ensure_clean(&repo, "rewrite")?;
+
+    let base_tree = from.peel_to_commit()?.tree()?;
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push(to.id())?;
+    revwalk.hide(from.id())?;
+    let oids = revwalk.collect::<Result<Vec<Oid>, git2::Error>>()?;
+
+    let mut rewritten: HashMap<Oid, Oid> = HashMap::new();
+    let mut progress = out.progress("rewriting", oids.len());
+
+    for oid in &oids {
+        progress.tick();
+
+        let commit = repo.find_commit(*oid)?;
+        let tree = commit.tree()?;
+
+        let new_tree = repo.find_tree(trimmed_tree(&repo, &tree, &added_lines(&repo, &base_tree, &tree, file_filters)?)?)?;
+        let new_parents: Vec<Commit> = commit
+            .parent_ids()
+            .map(|parent| repo.find_commit(*rewritten.get(&parent).unwrap_or(&parent)))
+            .collect::<Result<_, _>>()?;
+
+        let unchanged = new_tree.id() == tree.id() && new_parents.iter().map(Commit::id).eq(commit.parent_ids());
+        let new_oid = if unchanged {
+            *oid
+        } else {
+            let parents: Vec<&Commit> = new_parents.iter().collect();
+            repo.commit(
+                None,
+                &commit.author(),
+                &commit.committer(),
+                &String::from_utf8_lossy(commit.message_bytes()),
+                &new_tree,
+                &parents,
+            )?
+        };
+
+        rewritten.insert(*oid, new_oid);
+    }
+
+    drop(progress);
+
+    let changed: Vec<&Oid> = oids.iter().filter(|oid| rewritten[oid] != **oid).collect();
+    for oid in &changed {
+        out.report(format_args!("{} -> {}", oid, rewritten[oid]));
+    }
+
+    let new_tip = repo.find_commit(*rewritten.get(&to.id()).unwrap_or(&to.id()))?;
+    let message = format!("rtrim: rewrite {}", range);
+
+    if force {
+        let mut reference = repo.find_branch(&target, BranchType::Local)?.into_reference();
+        let checked_out = repo.head().ok().and_then(|head| head.name().map(String::from)) == reference.name().map(String::from);
+        reference.set_target(new_tip.id(), &message)?;
+
+        // the working tree was clean, so it can simply follow the moved branch
+        if checked_out {
+            repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+        }
+    } else {
+        repo.branch(&target, &new_tip, false)?;
+    }
+
+    out.info(format_args!(
+        "rtrim: {} of {} rewritten, {} now at {}",
+        plural(changed.len(), "commit"),
+        oids.len(),
+        target,
+        new_tip.id()
+    ));
+
+    Ok(Outcome::Clean)
+}
@@ -0,0 +1,660 @@
+use clap::ValueEnum;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::cli::{Cli, Eol};
+use crate::editorconfig;
+use crate::filter::{find_match, has_extension};
+use crate::regions::{default_regions, protected_lines, Region};
+use crate::trim::{split_line_ending, BOM};
+
+/// The Unicode `White_Space` characters without the line terminators, besides space and tab.
+pub const UNICODE_WHITESPACE: &[char] = &[
+    '\u{00a0}', '\u{1680}', '\u{2000}', '\u{2001}', '\u{2002}', '\u{2003}', '\u{2004}', '\u{2005}', '\u{2006}',
+    '\u{2007}', '\u{2008}', '\u{2009}', '\u{200a}', '\u{202f}', '\u{205f}', '\u{3000}',
+];
+
+/// The invisible characters `--detect-invisible` reports at the end of lines, with their Unicode names.
+pub const INVISIBLE_CHARS: &[(char, &str)] = &[
+    ('\u{00ad}', "SOFT HYPHEN"),
+    ('\u{200b}', "ZERO WIDTH SPACE"),
+    ('\u{200c}', "ZERO WIDTH NON-JOINER"),
+    ('\u{200d}', "ZERO WIDTH JOINER"),
+    ('\u{2060}', "WORD JOINER"),
+    ('\u{feff}', "ZERO WIDTH NO-BREAK SPACE"),
+];
+
+/// The extensions of Markdown files, `--markdown-ext` adds more.
+pub const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown"];
+
+/// The file names (lowercase) and extensions of files whose indentation has to be tabs, tab-in-indent skips them.
+pub const TAB_FILE_NAMES: &[&str] = &["makefile", "gnumakefile"];
+pub const TAB_EXTENSIONS: &[&str] = &["mk", "mak", "go", "tsv"];
+
+/// The extensions of files whose indentation can't be tabs, indent-with-non-tab skips them.
+pub const SPACE_EXTENSIONS: &[&str] = &["yaml", "yml"];
+
+/// The settings of an entry of the `rules` table of the config file, they override the global ones for its files.
+#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FileRules {
+    /// Trim the whitespaces at the end of lines.
+    pub trim: Option<bool>,
+    pub markdown_breaks: Option<bool>,
+    pub trim_eof_blank_lines: Option<bool>,
+    pub ensure_final_newline: Option<bool>,
+    /// `lf`, `crlf`, `native` or `keep` to leave the line endings alone.
+    pub eol: Option<String>,
+}
+
+impl FileRules {
+    fn apply_to(&self, rules: &mut Rules) {
+        if let Some(trim) = self.trim {
+            rules.blank_at_eol = trim;
+        }
+        if let Some(markdown_breaks) = self.markdown_breaks {
+            rules.hard_breaks = markdown_breaks;
+        }
+        if let Some(trim_eof_blank_lines) = self.trim_eof_blank_lines {
+            rules.trim_eof_blank_lines = trim_eof_blank_lines;
+        }
+        if let Some(ensure_final_newline) = self.ensure_final_newline {
+            rules.ensure_final_newline = ensure_final_newline;
+        }
+        if let Some(eol) = &self.eol {
+            rules.eol = Eol::from_str(eol, true).ok().map(|eol| eol.as_str());
+        }
+    }
+}
+
+/// The files an entry of the `rules` table is for.
+#[derive(Clone)]
+enum RulesTarget {
+    /// A key like `md` or `.md`, matched case-insensitively.
+    Extension(String),
+    /// Any other key, matched like the `exclude` patterns.
+    Glob(Pattern),
+}
+
+/// An entry of the `rules` table of the config file.
+#[derive(Clone)]
+pub struct RulesEntry {
+    pub key: String,
+    pub rules: FileRules,
+    target: RulesTarget,
+}
+
+impl RulesEntry {
+    pub fn new(key: &str, rules: &FileRules) -> Result<Self, String> {
+        if let Some(eol) = rules.eol.as_deref().filter(|eol| *eol != "keep") {
+            Eol::from_str(eol, true).map_err(|_| format!("`{}`: invalid eol `{}`", key, eol))?;
+        }
+
+        let extension = key.strip_prefix('.').unwrap_or(key);
+        let target = match extension.contains(['*', '?', '[', '/', '.']) {
+            true => RulesTarget::Glob(Pattern::new(key).map_err(|e| format!("`{}`: {}", key, e))?),
+            false => RulesTarget::Extension(extension.to_lowercase()),
+        };
+
+        Ok(RulesEntry { key: key.to_string(), rules: rules.clone(), target })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match &self.target {
+            RulesTarget::Extension(extension) => {
+                Path::new(path).extension().is_some_and(|e| e.to_string_lossy().to_lowercase() == *extension)
+            }
+            RulesTarget::Glob(pattern) => find_match(std::slice::from_ref(pattern), path).is_some(),
+        }
+    }
+
+    /// The characters the key matches literally, an extension counts like `*.md`.
+    fn specificity(&self) -> usize {
+        match &self.target {
+            RulesTarget::Extension(extension) => extension.chars().count() + 1,
+            RulesTarget::Glob(pattern) => pattern.as_str().chars().filter(|c| !matches!(c, '*' | '?')).count(),
+        }
+    }
+}
+
+/// What counts as trailing whitespace, shared by the scanner and the rewriter so that check and fix mode agree.
+#[derive(Clone)]
+pub struct Rules {
+    /// Trim the whitespaces at the end of lines, git's `blank-at-eol`.
+    pub blank_at_eol: bool,
+    /// Characters trimmed besides space and tab, empty keeps the byte-level ASCII behavior.
+    pub extra_whitespace: Vec<char>,
+    /// Also trim vertical tabs, form feeds and carriage returns that don't start a CRLF line ending.
+    pub control_whitespace: bool,
+    /// Keep the whitespaces of lines that have nothing else, e.g. an editor's indentation of blank lines.
+    pub keep_blank_line_indent: bool,
+    /// Remove the blank lines at the end of a file, keeping the final newline of the last line.
+    pub trim_eof_blank_lines: bool,
+    /// Squeeze the runs of added blank lines longer than this, the blank lines before the change are kept.
+    pub squeeze_blank_lines: Option<u32>,
+    /// Terminate a last line without newline with the file's dominant line ending.
+    pub ensure_final_newline: bool,
+    /// Extensions of the Markdown files whose hard line breaks are kept, empty without `--markdown-breaks`.
+    pub markdown_extensions: Vec<String>,
+    /// Keep two trailing spaces after text, they are a Markdown hard line break. Set per file by `for_path`.
+    pub hard_breaks: bool,
+    /// The protected regions per extension, empty without `--protect-regions`.
+    pub regions: BTreeMap<String, Region>,
+    /// The protected regions of the file, set by `for_path`.
+    pub region: Option<Region>,
+    /// Flag and remove the spaces before a tab in the indentation, git's `space-before-tab`.
+    pub space_before_tab: bool,
+    /// The columns between tab stops, git's `tabwidth`.
+    pub tab_width: u32,
+    /// Flag the tabs in the indentation, git's `tab-in-indent`. Turned off for Makefiles by `for_path`.
+    pub tab_in_indent: bool,
+    /// Expand the tabs in the indentation to the given tab stops.
+    pub fix_tabs: Option<u32>,
+    /// Flag a tab width of spaces in the indentation, git's `indent-with-non-tab`. Turned off for YAML by `for_path`.
+    pub indent_with_non_tab: bool,
+    /// Turn the spaces in the indentation into tabs, the alignment spaces after the last tab stop are kept.
+    pub fix_spaces: bool,
+    /// The line ending every line is converted to, `None` keeps them byte-for-byte.
+    pub eol: Option<&'static str>,
+    /// Report the lines that end differently than the rest of the file, only `eol` converts them.
+    pub mixed_line_endings: bool,
+    /// The invisible characters flagged at the end of lines, empty turns the check off.
+    pub invisible_chars: Vec<char>,
+    /// Remove the flagged invisible characters.
+    pub fix_invisible: bool,
+    /// Fill in the settings left at their defaults from the `.editorconfig` files, applied by `for_file`.
+    pub editorconfig: bool,
+    /// Decode UTF-16 files with a BOM, they are written back in their byte order.
+    pub utf16: bool,
+    /// The entries of the `rules` table, the more specific the later.
+    pub file_rules: Vec<RulesEntry>,
+}
+
+/// The last character of UTF-8 `content` and its length in bytes.
+fn last_char(content: &[u8]) -> Option<(char, usize)> {
+    (1..=content.len().min(4)).find_map(|len| {
+        let tail = std::str::from_utf8(&content[content.len() - len..]).ok()?;
+        let mut chars = tail.chars();
+        let c = chars.next()?;
+        chars.next().is_none().then_some((c, len))
+    })
+}
+
+/// The leading spaces and tabs of `content`, empty for a blank line like git does.
+fn indentation(content: &[u8]) -> &[u8] {
+    match content.iter().position(|b| *b != b' ' && *b != b'\t') {
+        Some(end) => &content[..end],
+        None => &[],
+    }
+}
+
+/// The width in columns of the leading spaces and tabs of `content`, with tab stops every `width` columns.
+fn indentation_width(content: &[u8], width: u32) -> usize {
+    indentation(content).iter().fold(0, |column, b| match b {
+        b'\t' => column + width as usize - column % width as usize,
+        _ => column + 1,
+    })
+}
+
+/// `content` with the tabs of its indentation expanded to spaces up to the next tab stop.
+fn expand_indentation(content: &[u8], width: u32) -> Vec<u8> {
+    let mut expanded = vec![b' '; indentation_width(content, width)];
+    expanded.extend_from_slice(&content[indentation(content).len()..]);
+
+    expanded
+}
+
+/// `content` with its indentation made of tabs up to the last tab stop, followed by the remaining spaces.
+fn tabify_indentation(content: &[u8], width: u32) -> Vec<u8> {
+    let columns = indentation_width(content, width);
+    let mut tabified = vec![b'\t'; columns / width as usize];
+    tabified.resize(tabified.len() + columns % width as usize, b' ');
+    tabified.extend_from_slice(&content[indentation(content).len()..]);
+
+    tabified
+}
+
+/// Parses a single character or its code point like `U+00A0`, line terminators are rejected.
+pub fn parse_whitespace_char(value: &str) -> Result<char, String> {
+    let c = match value.strip_prefix("U+").or_else(|| value.strip_prefix("u+")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+        None => {
+            let mut chars = value.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        }
+    };
+
+    match c {
+        Some('\n' | '\r' | '\u{0b}' | '\u{0c}' | '\u{85}' | '\u{2028}' | '\u{2029}') => {
+            Err(format!("`{}` is a line terminator", value.escape_default()))
+        }
+        Some(c) => Ok(c),
+        None => Err(format!("invalid character `{}`, expected one character or U+XXXX", value.escape_default())),
+    }
+}
+
+/// The code point of `c` like `U+00A0`.
+pub fn code_point(c: char) -> String {
+    format!("U+{:04X}", c as u32)
+}
+
+/// The code point and, for the default invisible characters, the Unicode name like `U+200B ZERO WIDTH SPACE`.
+pub fn char_name(c: char) -> String {
+    match INVISIBLE_CHARS.iter().find(|(invisible, _)| *invisible == c) {
+        Some((_, name)) => format!("{} {}", code_point(c), name),
+        None => code_point(c),
+    }
+}
+
+/// Only trailing spaces and tabs are trimmed.
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            blank_at_eol: true,
+            extra_whitespace: Vec::new(),
+            control_whitespace: false,
+            keep_blank_line_indent: false,
+            trim_eof_blank_lines: false,
+            squeeze_blank_lines: None,
+            ensure_final_newline: false,
+            markdown_extensions: Vec::new(),
+            hard_breaks: false,
+            regions: BTreeMap::new(),
+            region: None,
+            space_before_tab: false,
+            tab_width: 8,
+            tab_in_indent: false,
+            fix_tabs: None,
+            indent_with_non_tab: false,
+            fix_spaces: false,
+            eol: None,
+            mixed_line_endings: false,
+            invisible_chars: Vec::new(),
+            fix_invisible: false,
+            editorconfig: false,
+            utf16: false,
+            file_rules: Vec::new(),
+        }
+    }
+}
+
+impl Rules {
+    pub fn from_cli(cli: &Cli) -> Self {
+        let extra_whitespace = match (cli.unicode_whitespace, cli.whitespace_chars.is_empty()) {
+            (true, true) => UNICODE_WHITESPACE.to_vec(),
+            (true, false) => cli.whitespace_chars.iter().copied().filter(|c| *c != ' ' && *c != '\t').collect(),
+            (false, _) => Vec::new(),
+        };
+
+        Rules {
+            blank_at_eol: cli.blank_at_eol,
+            extra_whitespace,
+            control_whitespace: cli.control_whitespace,
+            keep_blank_line_indent: cli.keep_blank_line_indent,
+            trim_eof_blank_lines: cli.trim_eof_blank_lines,
+            squeeze_blank_lines: cli.squeeze_blank_lines,
+            ensure_final_newline: cli.ensure_final_newline,
+            markdown_extensions: match cli.markdown_breaks {
+                true => MARKDOWN_EXTENSIONS.iter().map(|e| e.to_string()).chain(cli.markdown_ext.iter().cloned()).collect(),
+                false => Vec::new(),
+            },
+            hard_breaks: false,
+            regions: match cli.protect_regions {
+                true => default_regions().into_iter().chain(cli.regions.clone()).collect(),
+                false => BTreeMap::new(),
+            },
+            region: None,
+            space_before_tab: cli.space_before_tab,
+            tab_width: cli.tab_width.unwrap_or(8),
+            tab_in_indent: cli.tab_in_indent || cli.fix_tabs.is_some(),
+            fix_tabs: cli.fix_tabs,
+            indent_with_non_tab: cli.indent_with_non_tab || cli.fix_spaces,
+            fix_spaces: cli.fix_spaces,
+            eol: cli.eol.map(|eol| eol.as_str()),
+            mixed_line_endings: cli.mixed_line_endings,
+            invisible_chars: match (cli.detect_invisible || cli.fix_invisible, cli.invisible_chars.is_empty()) {
+                (true, true) => INVISIBLE_CHARS.iter().map(|(c, _)| *c).collect(),
+                (true, false) => cli.invisible_chars.clone(),
+                (false, _) => Vec::new(),
+            },
+            fix_invisible: cli.fix_invisible,
+            editorconfig: cli.editorconfig,
+            utf16: cli.utf16,
+            file_rules: {
+                let mut file_rules = cli.file_rules.clone();
+                file_rules.sort_by_key(RulesEntry::specificity);
+                file_rules
+            },
+        }
+    }
+
+    /// The entries of the `rules` table for the file at `path`, the most specific one last.
+    pub fn matching_rules<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a RulesEntry> {
+        self.file_rules.iter().filter(move |entry| entry.matches(path))
+    }
+
+    /// The rules for the file at `path`.
+    pub fn for_path(&self, path: &Path) -> Self {
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let file_name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let path = &path.to_string_lossy();
+        let tabs_required = TAB_FILE_NAMES.contains(&file_name.as_str()) || TAB_EXTENSIONS.contains(&extension.as_str());
+
+        let mut rules = Rules {
+            hard_breaks: has_extension(&self.markdown_extensions, path),
+            region: self.regions.get(&extension).copied().filter(|region| *region != Region::Off),
+            tab_in_indent: self.tab_in_indent && !tabs_required,
+            indent_with_non_tab: self.indent_with_non_tab && !SPACE_EXTENSIONS.contains(&extension.as_str()),
+            ..self.clone()
+        };
+        // the more specific entries override the less specific ones, setting by setting
+        for entry in self.matching_rules(path) {
+            entry.rules.apply_to(&mut rules);
+        }
+
+        rules
+    }
+
+    /// Like `for_path`, with `editorconfig` the `.editorconfig` files from the file up to `workdir`'s root give
+    /// the final newline and the line ending where the configuration doesn't.
+    pub fn for_file(&self, workdir: &Path, path: &Path) -> Self {
+        let mut rules = self.for_path(path);
+
+        if self.editorconfig {
+            let properties = editorconfig::properties(workdir, path);
+            rules.ensure_final_newline |= properties.insert_final_newline == Some(true);
+            rules.eol = rules.eol.or(properties.end_of_line);
+        }
+
+        rules
+    }
+
+    /// The lines (1-based, ascending) whose trailing whitespaces are kept, `content` is the whole file.
+    pub fn protected_lines(&self, content: &[u8]) -> Vec<u32> {
+        self.region.map(|region| protected_lines(region, content)).unwrap_or_default()
+    }
+
+    /// `content` without its trailing whitespaces, `content` has no line ending.
+    /// With `keep_blank_line_indent` a line of whitespaces only is kept as it is.
+    pub fn trim<'a>(&self, content: &'a [u8]) -> &'a [u8] {
+        match self.trim_whitespace(content) {
+            trimmed if trimmed.is_empty() && self.keep_blank_line_indent => content,
+            trimmed => trimmed,
+        }
+    }
+
+    fn trim_whitespace<'a>(&self, content: &'a [u8]) -> &'a [u8] {
+        let mut end = content.len();
+
+        while end > 0 {
+            if content[end - 1] == b' ' || content[end - 1] == b'\t' {
+                end -= 1;
+                continue;
+            }
+            // `content` has no line ending, so a carriage return here isn't part of a CRLF
+            if self.control_whitespace && matches!(content[end - 1], b'\x0b' | b'\x0c' | b'\r') {
+                end -= 1;
+                continue;
+            }
+
+            match last_char(&content[..end]) {
+                Some((c, len)) if self.extra_whitespace.contains(&c) => end -= len,
+                _ => break,
+            }
+        }
+
+        // three or more spaces after text are trimmed down to the break, tabs aren't a break
+        let trailing = &content[end..];
+        if self.hard_breaks && end > 0 && trailing.len() >= 2 && trailing.iter().all(|b| *b == b' ') {
+            end += 2;
+        }
+
+        &content[..end]
+    }
+
+    /// The spaces before the last tab in the indentation of `content`, up to that tab, as a byte range.
+    /// `content` has no line ending.
+    pub fn space_before_tab(&self, content: &[u8]) -> Option<Range<usize>> {
+        if !self.space_before_tab {
+            return None;
+        }
+
+        let indentation = indentation(content);
+        let last_tab = indentation.iter().rposition(|b| *b == b'\t')?;
+        let first_space = indentation[..last_tab].iter().position(|b| *b == b' ')?;
+
+        Some(first_space..last_tab + 1)
+    }
+
+    /// The tabs in the indentation of `content`, from the first to the last one, as a byte range.
+    /// `content` has no line ending.
+    pub fn tab_in_indent(&self, content: &[u8]) -> Option<Range<usize>> {
+        if !self.tab_in_indent {
+            return None;
+        }
+
+        let indentation = indentation(content);
+        let first_tab = indentation.iter().position(|b| *b == b'\t')?;
+        let last_tab = indentation.iter().rposition(|b| *b == b'\t')?;
+
+        Some(first_tab..last_tab + 1)
+    }
+
+    /// The spaces after the last tab of the indentation of `content` if they span a tab width, as a byte range.
+    /// `content` has no line ending.
+    pub fn indent_with_non_tab(&self, content: &[u8]) -> Option<Range<usize>> {
+        if !self.indent_with_non_tab {
+            return None;
+        }
+
+        let indentation = indentation(content);
+        let start = indentation.iter().rposition(|b| *b == b'\t').map_or(0, |last_tab| last_tab + 1);
+
+        (indentation.len() - start >= self.tab_width as usize).then_some(start..indentation.len())
+    }
+
+    /// `content` with the whitespace errors of its indentation fixed, `None` if it has none.
+    fn fix_indentation(&self, content: &[u8]) -> Option<Vec<u8>> {
+        let mut fixed = self.space_before_tab(content).map(|range| self.drop_space_before_tab(content, range));
+
+        if let Some(width) = self.fix_tabs {
+            let current = fixed.as_deref().unwrap_or(content);
+            if self.tab_in_indent(current).is_some() {
+                fixed = Some(expand_indentation(current, width));
+            }
+        }
+        if self.fix_spaces {
+            let current = fixed.as_deref().unwrap_or(content);
+            if self.indent_with_non_tab(current).is_some() {
+                fixed = Some(tabify_indentation(current, self.tab_width));
+            }
+        }
+
+        fixed
+    }
+
+    /// `content` without the spaces before tabs in `range`.
+    fn drop_space_before_tab(&self, content: &[u8], range: Range<usize>) -> Vec<u8> {
+        let mut fixed = content[..range.start].to_vec();
+        let mut spaces = 0;
+
+        // like git, a tab width of spaces becomes a tab and the spaces left before a tab are dropped
+        for b in &content[range.clone()] {
+            if *b == b' ' {
+                spaces += 1;
+                if spaces == self.tab_width {
+                    fixed.push(b'\t');
+                    spaces = 0;
+                }
+            } else {
+                fixed.push(b'\t');
+                spaces = 0;
+            }
+        }
+        fixed.extend_from_slice(&content[range.end..]);
+
+        fixed
+    }
+
+    /// `content` with every whitespace error the rules fix, `content` has no line ending.
+    pub fn fix_line<'a>(&self, content: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut fixed = match self.strip_invisible(content) {
+            Some(stripped) => Cow::Owned(stripped),
+            None => Cow::Borrowed(content),
+        };
+        if self.blank_at_eol {
+            let len = self.trim(&fixed).len();
+            match &mut fixed {
+                Cow::Borrowed(content) => *content = &content[..len],
+                Cow::Owned(stripped) => stripped.truncate(len),
+            }
+        }
+
+        match self.fix_indentation(&fixed) {
+            Some(indented) => Cow::Owned(indented),
+            None => fixed,
+        }
+    }
+
+    /// The start of the trailing whitespaces and invisible characters of `content`, which has no line ending.
+    fn trailing_invisible_start(&self, content: &[u8]) -> usize {
+        let mut end = content.len();
+
+        while let Some((c, len)) = last_char(&content[..end]) {
+            let whitespace = c == ' '
+                || c == '\t'
+                || self.extra_whitespace.contains(&c)
+                || (self.control_whitespace && matches!(c, '\u{0b}' | '\u{0c}' | '\r'));
+            if !whitespace && !self.invisible_chars.contains(&c) {
+                break;
+            }
+            end -= len;
+        }
+
+        end
+    }
+
+    /// The byte offset of the first invisible character among the trailing whitespaces of `content`, and that
+    /// character. `content` has no line ending.
+    pub fn invisible(&self, content: &[u8]) -> Option<(usize, char)> {
+        if self.invisible_chars.is_empty() {
+            return None;
+        }
+
+        let start = self.trailing_invisible_start(content);
+        // the trailing characters were decoded one by one, so they are valid UTF-8
+        let trailing = std::str::from_utf8(&content[start..]).ok()?;
+
+        trailing.char_indices().find(|(_, c)| self.invisible_chars.contains(c)).map(|(i, c)| (start + i, c))
+    }
+
+    /// `content` without the flagged invisible characters, `None` if nothing is removed.
+    fn strip_invisible(&self, content: &[u8]) -> Option<Vec<u8>> {
+        let (start, _) = self.invisible(content).filter(|_| self.fix_invisible)?;
+        let trailing = String::from_utf8_lossy(&content[start..]);
+
+        let mut stripped = content[..start].to_vec();
+        stripped.extend(trailing.chars().filter(|c| !self.invisible_chars.contains(c)).collect::<String>().bytes());
+        Some(stripped)
+    }
+
+    /// Whether a raw line, with or without its `\n` or `\r\n` line ending, ends with whitespaces.
+    pub fn has_trailing_whitespace(&self, line: &[u8]) -> bool {
+        let (content, _) = split_line_ending(line);
+
+        self.blank_at_eol && self.trim(content).len() != content.len()
+    }
+
+    /// Whether `content`, a line without its line ending, is empty or whitespaces only.
+    pub fn is_blank(&self, content: &[u8]) -> bool {
+        self.trim_whitespace(content).is_empty()
+    }
+
+    /// The first line (1-based) of the run of blank lines `content` ends with, `None` if it ends with text.
+    pub fn blank_lines_at_eof(&self, content: &[u8]) -> Option<u32> {
+        let mut start = None;
+
+        for (line_no, line) in (1..).zip(content.split_inclusive(|b| *b == b'\n')) {
+            let (line_content, _) = split_line_ending(line);
+            let line_content = if line_no == 1 { line_content.strip_prefix(BOM).unwrap_or(line_content) } else { line_content };
+
+            if !self.is_blank(line_content) {
+                start = None;
+            } else if start.is_none() {
+                start = Some(line_no);
+            }
+        }
+
+        start
+    }
+
+    /// The first trailing character of `content` that isn't a space or a tab, for reporting.
+    pub fn unusual_whitespace(&self, content: &[u8]) -> Option<char> {
+        let trailing = &content[self.trim(content).len()..];
+
+        String::from_utf8_lossy(trailing).chars().find(|c| *c != ' ' && *c != '\t')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_spaces_and_tabs() {
+        let rules = Rules::default();
+
+        assert_eq!(rules.trim(b"a \t "), b"a");
+        assert_eq!(rules.trim(b" \t"), b"");
+        assert_eq!(rules.trim(b"a\x0b\x0c"), b"a\x0b\x0c");
+    }
+
+    #[test]
+    fn control_whitespace_trims_vt_ff_and_bare_cr() {
+        let rules = Rules { control_whitespace: true, ..Rules::default() };
+
+        assert_eq!(rules.trim(b"a \x0b\x0c\r\t"), b"a");
+        assert_eq!(rules.trim(b"a\x0bb"), b"a\x0bb");
+        assert!(rules.has_trailing_whitespace(b"a\r"));
+        assert!(!Rules::default().has_trailing_whitespace(b"a\r"));
+    }
+
+    #[test]
+    fn control_whitespace_keeps_crlf_line_endings() {
+        let rules = Rules { control_whitespace: true, ..Rules::default() };
+        let mut trimmed = Vec::new();
+        _ = crate::trim::trim_all_lines(b"a \r\r\nb\r\n", &rules, &mut trimmed).unwrap();
+
+        assert_eq!(trimmed, b"a\r\nb\r\n");
+    }
+
+    #[test]
+    fn keep_blank_line_indent() {
+        let rules = Rules { keep_blank_line_indent: true, ..Rules::default() };
+
+        assert_eq!(rules.trim(b"    "), b"    ");
+        assert_eq!(rules.trim(b"a  "), b"a");
+    }
+
+    #[test]
+    fn hard_breaks_keep_two_spaces() {
+        let rules = Rules { hard_breaks: true, ..Rules::default() };
+
+        assert_eq!(rules.trim(b"a  "), b"a  ");
+        assert_eq!(rules.trim(b"a    "), b"a  ");
+        assert_eq!(rules.trim(b"a \t"), b"a");
+        assert_eq!(rules.trim(b"   "), b"");
+    }
+
+    #[test]
+    fn extra_whitespace_is_trimmed() {
+        let rules = Rules { extra_whitespace: vec!['\u{a0}', '\u{3000}'], ..Rules::default() };
+
+        assert_eq!(rules.trim("a\u{a0} \u{3000}".as_bytes()), b"a");
+        assert_eq!(rules.trim("a\u{2003}".as_bytes()), "a\u{2003}".as_bytes());
+    }
+}
@@ -0,0 +1,1166 @@
+use glob::Pattern;
+use git2::{
+    AttrCheckFlags, AttrValue, Delta, DiffDelta, DiffFindOptions, DiffLineType, DiffOptions, FileMode, Index, Patch,
+    Repository, RepositoryState, StatusOptions, Tree,
+};
+use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::config::CONFIG_FILE_NAME;
+use crate::encoding::Utf16;
+use crate::error::RTrimError;
+use crate::filter::find_match;
+use crate::index;
+use crate::output::{display_path, Output};
+use crate::pathspec::Pathspecs;
+use crate::report::{SkipReason, SkippedFile};
+use crate::rules::{char_name, code_point, Rules};
+use crate::trim::{dominant_line_ending, eol_name, extra_blank_lines, split_line_ending, BOM};
+
+/// What is wrong with a line.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FindingKind {
+    TrailingWhitespace,
+    /// The line starts the run of blank lines at the end of the file.
+    BlankAtEof,
+    /// The last line has no line ending.
+    MissingFinalNewline,
+    /// Spaces before a tab in the indentation.
+    SpaceBeforeTab,
+    /// A tab in the indentation, the column is the first tab's.
+    TabInIndent,
+    /// A tab width of spaces in the indentation, the columns span the spaces after the last tab.
+    IndentWithNonTab,
+    /// The first line whose line ending isn't the one of `--eol`, every line ending of the file is converted.
+    LineEnding,
+    /// A line whose line ending differs from the one the rest of the file uses, only reported.
+    MixedLineEnding,
+    /// An invisible character like U+200B ZERO WIDTH SPACE at the end of a line.
+    InvisibleCharacter,
+    /// A blank line of a run longer than `--squeeze-blank-lines` allows, it is removed.
+    ExtraBlankLine,
+}
+
+impl FindingKind {
+    pub fn rule_id(self) -> &'static str {
+        match self {
+            FindingKind::TrailingWhitespace => "trailing-whitespace",
+            FindingKind::BlankAtEof => "blank-at-eof",
+            FindingKind::MissingFinalNewline => "missing-final-newline",
+            FindingKind::SpaceBeforeTab => "space-before-tab",
+            FindingKind::TabInIndent => "tab-in-indent",
+            FindingKind::IndentWithNonTab => "indent-with-non-tab",
+            FindingKind::LineEnding => "line-ending",
+            FindingKind::MixedLineEnding => "mixed-line-ending",
+            FindingKind::InvisibleCharacter => "invisible-character",
+            FindingKind::ExtraBlankLine => "extra-blank-line",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            FindingKind::TrailingWhitespace => "trailing whitespace",
+            FindingKind::BlankAtEof => "blank lines at end of file",
+            FindingKind::MissingFinalNewline => "missing final newline",
+            FindingKind::SpaceBeforeTab => "space before tab in indent",
+            FindingKind::TabInIndent => "tab in indent",
+            FindingKind::IndentWithNonTab => "indent with spaces",
+            FindingKind::LineEnding => "wrong line ending",
+            FindingKind::MixedLineEnding => "mixed line ending",
+            FindingKind::InvisibleCharacter => "invisible character",
+            FindingKind::ExtraBlankLine => "extra blank line",
+        }
+    }
+
+    /// Whether the finding is fixed by rewriting its line, unlike the findings about the end of the file.
+    pub fn is_line_rule(self) -> bool {
+        matches!(
+            self,
+            FindingKind::TrailingWhitespace
+                | FindingKind::SpaceBeforeTab
+                | FindingKind::TabInIndent
+                | FindingKind::IndentWithNonTab
+                | FindingKind::InvisibleCharacter
+        )
+    }
+}
+
+/// A staged line with trailing whitespaces.
+#[derive(Clone, Serialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    /// 1-based line number in the staged file.
+    pub line: u32,
+    /// 1-based column (in characters) where the trailing whitespaces start.
+    pub column: u32,
+    /// 1-based column (in characters) after the last trailing whitespace.
+    pub end_column: u32,
+    /// The line without its line ending, invalid UTF-8 is replaced for display.
+    #[serde(skip)]
+    pub content: String,
+    /// The first trailing whitespace character that is no space or tab, like `U+00A0`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub character: Option<String>,
+    /// The line ending of a mixed line ending finding, `LF` or `CRLF`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_ending: Option<&'static str>,
+}
+
+impl Finding {
+    /// `content` is a raw line in any ASCII compatible encoding, with or without its line ending.
+    pub fn new(line: u32, content: &[u8], rules: &Rules) -> Self {
+        // only a CRLF is a line ending, a bare carriage return can be trailing whitespace
+        let (content, _) = split_line_ending(content);
+        // a BOM would shift the columns of the first line
+        let content = if line == 1 { content.strip_prefix(BOM).unwrap_or(content) } else { content };
+        let trimmed = rules.trim(content);
+
+        Finding {
+            kind: FindingKind::TrailingWhitespace,
+            line,
+            column: String::from_utf8_lossy(trimmed).chars().count() as u32 + 1,
+            end_column: String::from_utf8_lossy(content).chars().count() as u32 + 1,
+            content: String::from_utf8_lossy(content).into_owned(),
+            character: rules.unusual_whitespace(content).map(code_point),
+            line_ending: None,
+        }
+    }
+
+    /// Whitespaces in the indentation, `range` are their byte offsets in `content`, which has no line ending.
+    pub fn indentation(kind: FindingKind, line: u32, content: &[u8], range: Range<usize>) -> Self {
+        Finding {
+            kind,
+            line,
+            column: range.start as u32 + 1,
+            end_column: range.end as u32 + 1,
+            content: String::from_utf8_lossy(content).into_owned(),
+            character: None,
+            line_ending: None,
+        }
+    }
+
+    /// The invisible character `c` at the byte `offset` of `content`, which has no line ending.
+    pub fn invisible(line: u32, content: &[u8], offset: usize, c: char) -> Self {
+        let column = String::from_utf8_lossy(&content[..offset]).chars().count() as u32 + 1;
+
+        Finding {
+            kind: FindingKind::InvisibleCharacter,
+            line,
+            column,
+            end_column: column + 1,
+            content: String::from_utf8_lossy(content).into_owned(),
+            character: Some(char_name(c)),
+            line_ending: None,
+        }
+    }
+
+    /// A line whose line ending has to be converted, `content` lacks the line ending. The column is the end of the line.
+    pub fn wrong_line_ending(line: u32, content: &[u8]) -> Self {
+        Finding { kind: FindingKind::LineEnding, ..Finding::missing_final_newline(line, content) }
+    }
+
+    /// A line ending with `line_ending` unlike the rest of the file, `content` lacks the line ending.
+    pub fn mixed_line_ending(line: u32, content: &[u8], line_ending: &str) -> Self {
+        Finding {
+            kind: FindingKind::MixedLineEnding,
+            line_ending: Some(eol_name(line_ending)),
+            ..Finding::missing_final_newline(line, content)
+        }
+    }
+
+    /// The first of the blank lines at the end of a file.
+    pub fn blank_at_eof(line: u32) -> Self {
+        Finding {
+            kind: FindingKind::BlankAtEof,
+            line,
+            column: 1,
+            end_column: 1,
+            content: String::new(),
+            character: None,
+            line_ending: None,
+        }
+    }
+
+    /// A blank line that is removed to squeeze a run of blank lines.
+    pub fn extra_blank_line(line: u32) -> Self {
+        Finding { kind: FindingKind::ExtraBlankLine, ..Finding::blank_at_eof(line) }
+    }
+
+    /// The last line of a file, `content` lacks a line ending. The column is the end of the line.
+    pub fn missing_final_newline(line: u32, content: &[u8]) -> Self {
+        let content = if line == 1 { content.strip_prefix(BOM).unwrap_or(content) } else { content };
+        let column = String::from_utf8_lossy(content).chars().count() as u32 + 1;
+
+        Finding {
+            kind: FindingKind::MissingFinalNewline,
+            line,
+            column,
+            end_column: column,
+            content: String::from_utf8_lossy(content).into_owned(),
+            character: None,
+            line_ending: None,
+        }
+    }
+}
+
+/// The staged lines with trailing whitespaces per repo relative file path, ordered by line number.
+pub type StagedLines = BTreeMap<PathBuf, Vec<Finding>>;
+
+/// A repository path from git's raw bytes, which needn't be valid UTF-8.
+pub fn bytes_path(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// The working tree file of a repository path. With core.precomposeunicode git reports names precomposed (NFC)
+/// that the file system may store decomposed (NFD), or the other way around, the file is looked up in the other
+/// form then. The index keeps the name git reported.
+pub fn working_tree_path(workdir: &Path, path: &Path) -> PathBuf {
+    let full_path = workdir.join(path);
+    let Some(name) = path.to_str().filter(|name| !name.is_ascii()) else {
+        return full_path;
+    };
+    if fs::symlink_metadata(&full_path).is_ok() {
+        return full_path;
+    }
+
+    for alternative in [name.nfc().collect::<String>(), name.nfd().collect::<String>()] {
+        let candidate = workdir.join(&alternative);
+        if alternative != name && fs::symlink_metadata(&candidate).is_ok() {
+            log::debug!("{}: found in the working tree as {}", name, candidate.display());
+            return candidate;
+        }
+    }
+
+    full_path
+}
+
+/// The tree the index is diffed against.
+#[derive(Clone, Copy)]
+pub enum Base<'a> {
+    /// HEAD's tree, none for an unborn HEAD.
+    Head,
+    /// The tree of HEAD's first parent so that the amended commit is covered as a whole,
+    /// none when amending the root commit.
+    Amend,
+    /// The tree of any revision.
+    Rev(&'a str),
+}
+
+impl Base<'_> {
+    fn describe(&self) -> &str {
+        match self {
+            Base::Head => "HEAD",
+            Base::Amend => "HEAD's parent",
+            Base::Rev(rev) => rev,
+        }
+    }
+
+    fn resolve<'r>(&self, repo: &'r Repository) -> Result<Option<Tree<'r>>, RTrimError> {
+        match self {
+            Base::Head => Ok(match repo.head() {
+                Ok(r) => Option::Some(r.peel_to_tree()?),
+                Err(_) => Option::None,
+            }),
+            Base::Amend => {
+                let head = repo
+                    .head()
+                    .and_then(|r| r.peel_to_commit())
+                    .map_err(|e| RTrimError::Config(format!("nothing to amend: {}", e.message())))?;
+
+                match head.parent_count() {
+                    0 => Ok(None),
+                    _ => Ok(Some(head.parent(0)?.tree()?)),
+                }
+            }
+            Base::Rev(rev) => repo
+                .revparse_single(rev)
+                .and_then(|object| object.peel_to_tree())
+                .map(Some)
+                .map_err(|e| RTrimError::Config(format!("invalid revision '{}': {}", rev, e.message()))),
+        }
+    }
+}
+
+/// What the scans diff and which files and lines they consider.
+pub struct ScanOptions<'a> {
+    pub base: Base<'a>,
+    pub path_filters: &'a [String],
+    /// Consider every line of a changed file, not only the changed ones.
+    pub whole_file: bool,
+    /// Diff the working tree against the index instead of the index against `base`.
+    pub working_tree: bool,
+    /// Also scan untracked files that are not ignored.
+    pub include_untracked: bool,
+    /// Scan the whole working tree file of intent-to-add entries instead of skipping them.
+    pub include_intent_to_add: bool,
+    /// Larger files are skipped without reading them.
+    pub max_file_size: Option<u64>,
+    /// Files that are scanned even if they look binary.
+    pub force_text: &'a [Pattern],
+    pub rules: &'a Rules,
+}
+
+/// The result of a scan.
+pub struct Scan {
+    pub files: StagedLines,
+    pub files_scanned: usize,
+    /// Files over `max_file_size`.
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl ScanOptions<'_> {
+    /// Warns about and records a file over `max_file_size`.
+    fn skip_large(&self, path: &Path, size: u64, skipped: &mut Vec<SkippedFile>, out: &Output) -> bool {
+        match self.max_file_size {
+            Some(max_file_size) if size > max_file_size => {
+                out.warning(format_args!(
+                    "{}: skipped, {} bytes exceed --max-file-size {}",
+                    display_path(path), size, max_file_size
+                ));
+                skipped.push(SkippedFile::new(path, &[], SkipReason::Size));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Reports and records a binary file unless it matches `force_text`.
+    fn skip_binary(&self, path: &Path, binary: bool, skipped: &mut Vec<SkippedFile>, out: &Output) -> bool {
+        if !binary || find_match(self.force_text, &path.to_string_lossy()).is_some() {
+            return false;
+        }
+
+        out.verbose(format_args!("{}: skipped as binary, use --force-text to scan it", display_path(path)));
+        skipped.push(SkippedFile::new(path, &[], SkipReason::Binary));
+        true
+    }
+
+    /// Whether `content` is UTF-16 with a BOM that `--utf16` decodes, it isn't binary then.
+    fn is_utf16(&self, content: &[u8]) -> bool {
+        self.rules.utf16 && Utf16::detect(content).is_some()
+    }
+
+    /// `content` decoded to UTF-8 if `is_utf16`, warns about and records a file that isn't valid UTF-16.
+    fn decode<'c>(
+        &self,
+        path: &Path,
+        content: &'c [u8],
+        skipped: &mut Vec<SkippedFile>,
+        out: &Output,
+    ) -> Option<Cow<'c, [u8]>> {
+        let Some(utf16) = Utf16::detect(content).filter(|_| self.rules.utf16) else {
+            return Some(Cow::Borrowed(content));
+        };
+
+        match utf16.decode(content) {
+            Some(decoded) => {
+                log::debug!("{}: decoded from {}", path.display(), utf16.name());
+                Some(Cow::Owned(decoded))
+            }
+            None => {
+                out.warning(format_args!("{}: skipped, not valid {}", display_path(path), utf16.name()));
+                skipped.push(SkippedFile::new(path, &[], SkipReason::Encoding));
+                None
+            }
+        }
+    }
+
+    /// The path of a changed file and whether it is intent-to-add, `None` for the deltas that aren't scanned at all:
+    /// deletions, symlinks, submodules, Git LFS files and the paths outside the pathspecs.
+    fn changed_path(
+        &self,
+        repo: &Repository,
+        index: Option<&Index>,
+        pathspecs: &Pathspecs,
+        delta_index: usize,
+        delta: &DiffDelta,
+        out: &Output,
+    ) -> Option<(PathBuf, bool)> {
+        // a deleted file has no lines left to trim
+        if delta.status() == Delta::Deleted {
+            log::debug!("delta {} is a deletion, skipped", delta_index);
+            return None;
+        }
+        let new_file = delta.new_file();
+        let Some(path) = new_file.path_bytes().map(bytes_path) else {
+            log::debug!("delta {} has no new path, skipped", delta_index);
+            return None;
+        };
+
+        // the pathspec magic libgit2 doesn't know
+        if !pathspecs.matches(&path.to_string_lossy()) {
+            return None;
+        }
+        // the content of a symlink is the path it points to
+        if new_file.mode() == FileMode::Link {
+            out.verbose(format_args!("{}: skipped as symlink", display_path(&path)));
+            return None;
+        }
+        // a submodule bump only changes the commit it points to, --recurse-submodules scans its changes
+        if new_file.mode() == FileMode::Commit {
+            out.verbose(format_args!("{}: skipped as submodule", display_path(&path)));
+            return None;
+        }
+        // the working tree has the real content, the staged side the pointer
+        let pointer =
+            (!self.working_tree && new_file.size() < 1024).then(|| repo.find_blob(new_file.id()).ok()).flatten();
+        if is_lfs_pointer(repo, &path, pointer.as_ref().map(|blob| blob.content())) {
+            out.verbose(format_args!("{}: skipped as Git LFS pointer", display_path(&path)));
+            return None;
+        }
+
+        let intent_to_add =
+            index.and_then(|index| index.get_path(&path, 0)).is_some_and(|entry| index::is_intent_to_add(&entry));
+        if intent_to_add && !self.include_intent_to_add {
+            out.verbose(format_args!(
+                "{}: skipped as intent-to-add, use --include-intent-to-add to trim it",
+                display_path(&path)
+            ));
+            return None;
+        }
+
+        Some((path, intent_to_add))
+    }
+
+    /// The findings of the whole working tree file of an intent-to-add entry, none if it is skipped.
+    fn intent_to_add_findings(
+        &self,
+        repo: &Repository,
+        path: &Path,
+        skipped: &mut Vec<SkippedFile>,
+        protected: &mut Vec<SkippedFile>,
+        out: &Output,
+    ) -> Vec<Finding> {
+        let Some(content) = repo.workdir().and_then(|workdir| fs::read(working_tree_path(workdir, path)).ok()) else {
+            log::debug!("{}: intent-to-add, but not in the working tree", path.display());
+            return Vec::new();
+        };
+        if self.skip_large(path, content.len() as u64, skipped, out)
+            || self.skip_binary(path, is_binary_unless_utf16(&content, self.rules.utf16), skipped, out)
+        {
+            return Vec::new();
+        }
+        let Some(content) = self.decode(path, &content, skipped, out) else {
+            return Vec::new();
+        };
+
+        let rules = &file_rules(repo, self.rules, path, out);
+        let mut findings = content_findings(&content, rules);
+        skip_protected(path, &content, &mut findings, rules, protected, out);
+        log::trace!("{}: flagged {} in the intent-to-add file", path.display(), findings.len());
+
+        findings
+    }
+
+    /// How the changed lines of a file are read, it is skipped if it is too large or binary.
+    fn changed_content(
+        &self,
+        repo: &Repository,
+        delta: &DiffDelta,
+        path: &Path,
+        skipped: &mut Vec<SkippedFile>,
+        out: &Output,
+    ) -> Result<ChangedContent, RTrimError> {
+        let new_file = delta.new_file();
+        let workdir = repo.workdir().filter(|_| self.working_tree);
+
+        // the size of the working tree side isn't always known
+        let size = match workdir.filter(|_| new_file.size() == 0) {
+            Some(workdir) => fs::metadata(working_tree_path(workdir, path)).map(|m| m.len()).unwrap_or(0),
+            None => new_file.size(),
+        };
+        if self.skip_large(path, size, skipped, out) {
+            return Ok(ChangedContent::Skipped);
+        }
+
+        // the diff is forced to text, so binary files are told apart here like git does
+        let binary = match workdir {
+            Some(workdir) => fs::read(working_tree_path(workdir, path)).is_ok_and(|content| is_binary(&content)),
+            None => repo.find_blob(new_file.id()).is_ok_and(|blob| blob.is_binary()),
+        };
+        if binary && self.rules.utf16 {
+            let content = match workdir {
+                Some(workdir) => fs::read(working_tree_path(workdir, path))?,
+                None => repo.find_blob(new_file.id())?.content().to_vec(),
+            };
+            if self.is_utf16(&content) {
+                let Some(new) = self.decode(path, &content, skipped, out) else {
+                    return Ok(ChangedContent::Skipped);
+                };
+                let old = match delta.old_file().id() {
+                    id if id.is_zero() => Vec::new(),
+                    id => repo.find_blob(id)?.content().to_vec(),
+                };
+                // an old side that wasn't UTF-16 yet is diffed as it is
+                let old = Utf16::detect(&old).and_then(|utf16| utf16.decode(&old)).unwrap_or(old);
+                return Ok(ChangedContent::Utf16 { old, new: new.into_owned() });
+            }
+        }
+
+        if self.skip_binary(path, binary, skipped, out) {
+            return Ok(ChangedContent::Skipped);
+        }
+        Ok(ChangedContent::Diff)
+    }
+}
+
+/// How the changed lines of a file are read.
+enum ChangedContent {
+    /// The file is skipped, it was already reported.
+    Skipped,
+    /// From the patch of the diff.
+    Diff,
+    /// From a patch of the decoded sides of a UTF-16 file, git would diff it as binary.
+    Utf16 { old: Vec<u8>, new: Vec<u8> },
+}
+
+/// Git's heuristic, content with a NUL byte in the first 8000 bytes is binary.
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|b| *b == 0)
+}
+
+/// Like `is_binary`, but UTF-16 with a BOM is text if `utf16` is set.
+pub fn is_binary_unless_utf16(content: &[u8], utf16: bool) -> bool {
+    is_binary(content) && !(utf16 && Utf16::detect(content).is_some())
+}
+
+/// Whether `content` still has the `<<<<<<<` and `>>>>>>>` lines of a conflict.
+fn has_conflict_markers(content: &[u8]) -> bool {
+    let is_marker = |line: &[u8], marker: &[u8]| {
+        line.strip_prefix(marker).is_some_and(|rest| matches!(rest.first(), None | Some(b' ' | b'\r')))
+    };
+    let mut lines = content.split(|b| *b == b'\n');
+
+    lines.any(|line| is_marker(line, b"<<<<<<<")) && lines.any(|line| is_marker(line, b">>>>>>>"))
+}
+
+/// Warns about and records a file with an unresolved conflict, trimming it could hide the conflict when it is staged.
+fn skip_conflict(path: &Path, description: &str, skipped: &mut Vec<SkippedFile>, out: &Output) {
+    out.warning(format_args!("{}: skipped, {}", display_path(path), description));
+    skipped.push(SkippedFile::new(path, &[], SkipReason::Conflict));
+}
+
+/// Whether `path` is stored in Git LFS or `content` is a pointer file, rewriting it would break the pointer.
+/// Pointer files are smaller than 1024 bytes, larger content isn't looked at.
+fn is_lfs_pointer(repo: &Repository, path: &Path, content: Option<&[u8]>) -> bool {
+    let filter = repo.get_attr(path, "filter", AttrCheckFlags::FILE_THEN_INDEX).ok().flatten();
+    if filter == Some("lfs") {
+        return true;
+    }
+
+    content.is_some_and(|content| content.len() < 1024 && content.starts_with(b"version https://git-lfs.github.com/spec/v1\n"))
+}
+
+/// Whether a merge, cherry-pick, revert or rebase is in progress, files can have conflict markers then.
+fn is_merging(repo: &Repository) -> bool {
+    let state = repo.state();
+    if state != RepositoryState::Clean {
+        log::info!("repository state {:?}, files with conflicts are skipped", state);
+    }
+    state != RepositoryState::Clean
+}
+
+/// The findings of a raw line, with or without its line ending, in the order of their columns.
+pub fn line_findings(line: u32, raw_line: &[u8], rules: &Rules) -> Vec<Finding> {
+    let (content, _) = split_line_ending(raw_line);
+    let content = if line == 1 { content.strip_prefix(BOM).unwrap_or(content) } else { content };
+    let mut findings: Vec<Finding> = Vec::new();
+
+    if let Some(range) = rules.space_before_tab(content) {
+        findings.push(Finding::indentation(FindingKind::SpaceBeforeTab, line, content, range));
+    }
+    if let Some(range) = rules.tab_in_indent(content) {
+        findings.push(Finding::indentation(FindingKind::TabInIndent, line, content, range));
+    }
+    if let Some(range) = rules.indent_with_non_tab(content) {
+        findings.push(Finding::indentation(FindingKind::IndentWithNonTab, line, content, range));
+    }
+    if let Some((offset, c)) = rules.invisible(content) {
+        findings.push(Finding::invisible(line, content, offset, c));
+    }
+    if rules.has_trailing_whitespace(content) {
+        findings.push(Finding::new(line, content, rules));
+    }
+
+    findings
+}
+
+/// The finding for the last line of `content` if it lacks a line ending, an empty file needs none.
+fn missing_final_newline(content: &[u8]) -> Option<Finding> {
+    if content.is_empty() || content.ends_with(b"\n") {
+        return None;
+    }
+
+    let last_line = content.split(|b| *b == b'\n').next_back().unwrap_or_default();
+    let line_no = content.iter().filter(|b| **b == b'\n').count() as u32 + 1;
+
+    Some(Finding::missing_final_newline(line_no, last_line))
+}
+
+/// The finding for the first line of `content` whose line ending isn't `eol`.
+fn wrong_line_ending(content: &[u8], eol: &str) -> Option<Finding> {
+    (1..).zip(content.split_inclusive(|b| *b == b'\n')).find_map(|(line_no, line)| {
+        let (line_content, line_ending) = split_line_ending(line);
+        (!line_ending.is_empty() && line_ending != eol.as_bytes()).then(|| Finding::wrong_line_ending(line_no, line_content))
+    })
+}
+
+/// The findings for the `lines` (1-based, ascending) of `content` that end differently than the other lines,
+/// every line if `lines` is `None`. Only a file without other lines is compared to the lines themselves.
+fn mixed_line_endings(content: &[u8], lines: Option<&[u32]>) -> Vec<Finding> {
+    let is_checked = |line_no: u32| lines.is_none_or(|lines| lines.binary_search(&line_no).is_ok());
+    let file_lines: Vec<(u32, &[u8], &[u8])> = (1..)
+        .zip(content.split_inclusive(|b| *b == b'\n'))
+        .map(|(line_no, line)| {
+            let (line_content, line_ending) = split_line_ending(line);
+            (line_no, line_content, line_ending)
+        })
+        .filter(|(_, _, line_ending)| !line_ending.is_empty())
+        .collect();
+
+    // the lines of `lines` are compared to the other lines, a file with only those to each other
+    let count = |endings: Vec<&[u8]>| {
+        let crlf = endings.iter().filter(|ending| **ending == b"\r\n").count();
+        (crlf, endings.len() - crlf)
+    };
+    let other = file_lines.iter().filter(|(line_no, _, _)| lines.is_some() && !is_checked(*line_no));
+    let (crlf, lf) = match count(other.map(|(_, _, ending)| *ending).collect()) {
+        (0, 0) => count(file_lines.iter().map(|(_, _, ending)| *ending).collect()),
+        counts => counts,
+    };
+    let dominant = dominant_line_ending(crlf, lf);
+
+    file_lines
+        .iter()
+        .filter(|(line_no, _, line_ending)| is_checked(*line_no) && *line_ending != dominant.as_bytes())
+        .map(|(line_no, line_content, line_ending)| {
+            Finding::mixed_line_ending(*line_no, line_content, &String::from_utf8_lossy(line_ending))
+        })
+        .collect()
+}
+
+/// The findings for the blank lines removed when squeezing the runs of more than `max` blank lines of `content`,
+/// only the `lines` (1-based, ascending) are removed, every line if `lines` is `None`.
+fn squeezed_blank_lines(content: &[u8], max: u32, lines: Option<&[u32]>, rules: &Rules) -> Vec<Finding> {
+    let blank: Vec<bool> = (1..)
+        .zip(content.split_inclusive(|b| *b == b'\n'))
+        .map(|(line_no, line)| {
+            let (line_content, _) = split_line_ending(line);
+            let line_content = if line_no == 1 { line_content.strip_prefix(BOM).unwrap_or(line_content) } else { line_content };
+            rules.is_blank(line_content)
+        })
+        .collect();
+    let removable = |line_no: u32| lines.is_none_or(|lines| lines.binary_search(&line_no).is_ok());
+
+    extra_blank_lines(&blank, max, removable).into_iter().map(Finding::extra_blank_line).collect()
+}
+
+/// The rules for `path`, files with the `-text` attribute keep their line endings.
+fn file_rules(repo: &Repository, rules: &Rules, path: &Path, out: &Output) -> Rules {
+    print_matching_rules(rules, path, out);
+    let mut rules = match repo.workdir() {
+        Some(workdir) => rules.for_file(workdir, path),
+        None => rules.for_path(path),
+    };
+
+    if rules.eol.is_some() || rules.mixed_line_endings {
+        let text = repo.get_attr(path, "text", AttrCheckFlags::FILE_THEN_INDEX).ok().flatten();
+        if matches!(AttrValue::from_string(text), AttrValue::False) {
+            log::debug!("{}: -text, the line endings are kept", path.display());
+            rules.eol = None;
+            rules.mixed_line_endings = false;
+        }
+    }
+
+    rules
+}
+
+/// Tells which entries of the `rules` table apply to `path`, the last one is the most specific.
+pub fn print_matching_rules(rules: &Rules, path: &Path, out: &Output) {
+    let keys: Vec<String> =
+        rules.matching_rules(&path.to_string_lossy()).map(|entry| format!("`{}`", entry.key)).collect();
+
+    if !keys.is_empty() {
+        out.verbose(format_args!("{}: rules {} from {}", display_path(path), keys.join(", "), CONFIG_FILE_NAME));
+    }
+}
+
+/// The findings of every line of a file's content.
+fn content_findings(content: &[u8], rules: &Rules) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = (1..)
+        .zip(content.split_inclusive(|b| *b == b'\n'))
+        .flat_map(|(line_no, line)| line_findings(line_no, line, rules))
+        .collect();
+
+    if rules.trim_eof_blank_lines {
+        findings.extend(rules.blank_lines_at_eof(content).map(Finding::blank_at_eof));
+    }
+    if rules.ensure_final_newline {
+        findings.extend(missing_final_newline(content));
+    }
+    if let Some(eol) = rules.eol {
+        findings.extend(wrong_line_ending(content, eol));
+    }
+    if rules.mixed_line_endings {
+        findings.extend(mixed_line_endings(content, None));
+    }
+    if let Some(max) = rules.squeeze_blank_lines {
+        findings.extend(squeezed_blank_lines(content, max, None, rules));
+    }
+    findings.sort_by_key(|f| f.line);
+
+    findings
+}
+
+/// Moves the line findings inside the protected regions of `content`, the whole file, to `skipped`.
+fn skip_protected(
+    path: &Path,
+    content: &[u8],
+    findings: &mut Vec<Finding>,
+    rules: &Rules,
+    skipped: &mut Vec<SkippedFile>,
+    out: &Output,
+) {
+    if rules.region.is_none() || findings.is_empty() {
+        return;
+    }
+
+    let protected_lines = rules.protected_lines(content);
+    let (protected, kept): (Vec<Finding>, Vec<Finding>) = findings.drain(..).partition(|f| {
+        f.kind.is_line_rule() && protected_lines.binary_search(&f.line).is_ok()
+    });
+    *findings = kept;
+
+    if !protected.is_empty() {
+        for finding in &protected {
+            out.verbose(format_args!("{}:{}: skipped (protected region)", display_path(path), finding.line));
+        }
+        skipped.push(SkippedFile::new(path, &protected, SkipReason::Protected));
+    }
+}
+
+/// The added lines of a patch and their findings.
+struct AddedLines {
+    findings: Vec<Finding>,
+    lines: Vec<u32>,
+    /// The last added blank line, it might be part of the blank lines at the end of the file.
+    last_blank: Option<u32>,
+}
+
+impl AddedLines {
+    /// Checks the line rules on every added line of `patch`.
+    fn of(patch: &Patch, path: &Path, rules: &Rules) -> Result<AddedLines, RTrimError> {
+        let mut added = AddedLines { findings: Vec::new(), lines: Vec::new(), last_blank: None };
+
+        for hunk_index in 0..patch.num_hunks() {
+            for line_index in 0..patch.num_lines_in_hunk(hunk_index)? {
+                let diff_line = patch.line_in_hunk(hunk_index, line_index)?;
+
+                // context lines have a new line number too but weren't changed, and the
+                // `\ No newline at end of file` markers aren't content, the last line before them is
+                if diff_line.origin_value() != DiffLineType::Addition {
+                    continue;
+                }
+
+                if let Some(line_no) = diff_line.new_lineno() {
+                    let raw_line = diff_line.content();
+
+                    let line_findings = line_findings(line_no, raw_line, rules);
+                    if !line_findings.is_empty() {
+                        log::trace!("flagged {}:{}", path.display(), line_no);
+                        added.findings.extend(line_findings);
+                    }
+
+                    added.lines.push(line_no);
+                    if rules.is_blank(split_line_ending(raw_line).0) {
+                        added.last_blank = Some(line_no);
+                    }
+                }
+            }
+        }
+
+        Ok(added)
+    }
+
+    /// Only a run with added blank lines can be squeezed.
+    fn squeeze(&self, rules: &Rules) -> Option<u32> {
+        rules.squeeze_blank_lines.filter(|_| self.last_blank.is_some())
+    }
+
+    /// Blank lines that were already committed at the end of the file are left alone.
+    fn last_blank_at_eof(&self, rules: &Rules) -> Option<u32> {
+        self.last_blank.filter(|_| rules.trim_eof_blank_lines)
+    }
+
+    fn mixed(&self, rules: &Rules) -> bool {
+        rules.mixed_line_endings && !self.lines.is_empty()
+    }
+
+    /// Whether the file rules or the protected regions need the whole new content.
+    fn needs_content(&self, rules: &Rules) -> bool {
+        let protect = rules.region.is_some() && !self.findings.is_empty();
+
+        self.last_blank_at_eof(rules).is_some()
+            || rules.ensure_final_newline
+            || rules.eol.is_some()
+            || self.mixed(rules)
+            || self.squeeze(rules).is_some()
+            || protect
+    }
+
+    /// Adds the findings of the file rules on the new `content`.
+    fn extend_from_content(&mut self, path: &Path, content: &[u8], rules: &Rules) {
+        if let Some(last_blank) = self.last_blank_at_eof(rules) {
+            if let Some(start) = rules.blank_lines_at_eof(content).filter(|start| *start <= last_blank) {
+                log::trace!("flagged the blank lines at the end of {} from line {}", path.display(), start);
+                self.findings.push(Finding::blank_at_eof(start));
+            }
+        }
+        if rules.ensure_final_newline {
+            self.findings.extend(missing_final_newline(content));
+        }
+        if let Some(eol) = rules.eol {
+            self.findings.extend(wrong_line_ending(content, eol));
+        }
+        if self.mixed(rules) {
+            self.findings.extend(mixed_line_endings(content, Some(&self.lines)));
+        }
+        if let Some(max) = self.squeeze(rules) {
+            self.findings.extend(squeezed_blank_lines(content, max, Some(&self.lines), rules));
+        }
+
+        self.findings.sort_by_key(|f| f.line);
+    }
+}
+
+/// Returns the changed lines with trailing whitespaces per file.
+pub fn get_staged_lines_with_trailing_spaces(
+    repo: &Repository,
+    options: &ScanOptions,
+    out: &Output,
+) -> Result<Scan, RTrimError> {
+    let mut result: StagedLines = BTreeMap::new();
+    let mut skipped: Vec<SkippedFile> = Vec::new();
+
+    //get index
+    let index = Option::Some(repo.index()?);
+
+    // files with findings in protected regions, they are still scanned
+    let mut protected: Vec<SkippedFile> = Vec::new();
+    let merging = is_merging(repo);
+
+    let mut diff_options = DiffOptions::new();
+
+    let pathspecs = Pathspecs::parse(options.path_filters)?;
+
+    for path_filter in pathspecs.for_libgit2() {
+        diff_options.pathspec(path_filter);
+    }
+
+    diff_options.force_text(true);
+
+    if options.include_untracked {
+        diff_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+    }
+
+    //get diff
+    let mut diff_result = if options.working_tree {
+        log::debug!("diffing the working tree against the index");
+        repo.diff_index_to_workdir(index.as_ref(), Some(&mut diff_options))?
+    } else {
+        //get base tree
+        let head_tree = options.base.resolve(repo)?;
+        log::debug!(
+            "diffing the index against {}",
+            if head_tree.is_some() { options.base.describe() } else { "an empty tree" }
+        );
+        repo.diff_tree_to_index(head_tree.as_ref(), index.as_ref(), Some(&mut diff_options))?
+    };
+
+    // a renamed file would be a deletion and an addition of every line of it, only its edits are changes
+    diff_result.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    //iterate over the deltas of the diff_result and put lines with trailing spaces in the result
+    let delta_count = diff_result.deltas().len();
+    let mut progress = out.progress("scanning", delta_count);
+    let mut files_scanned: usize = 0;
+
+    for delta_index in 0..delta_count {
+        progress.tick();
+
+        // checked before the patch is computed, which reads the whole file
+        let Some(delta) = diff_result.get_delta(delta_index) else {
+            continue;
+        };
+        let changed_path = options.changed_path(repo, index.as_ref(), &pathspecs, delta_index, &delta, out);
+        let Some((path, intent_to_add)) = changed_path else {
+            continue;
+        };
+
+        files_scanned += 1;
+        if delta.status() == Delta::Conflicted {
+            skip_conflict(&path, "unresolved conflict", &mut skipped, out);
+            continue;
+        }
+
+        // nothing of it is staged yet, so its whole working tree file is scanned
+        if intent_to_add && !options.working_tree {
+            let findings = options.intent_to_add_findings(repo, &path, &mut skipped, &mut protected, out);
+            if !findings.is_empty() {
+                result.insert(path, findings);
+            }
+            continue;
+        }
+
+        // the decoded sides of a UTF-16 file, git would diff it as binary
+        let utf16_sides = match options.changed_content(repo, &delta, &path, &mut skipped, out)? {
+            ChangedContent::Skipped => continue,
+            ChangedContent::Diff => None,
+            ChangedContent::Utf16 { old, new } => Some((path, old, new)),
+        };
+
+        // unchanged files have no patch
+        let patch = match &utf16_sides {
+            Some((path, old, new)) => {
+                let path = Some(path.as_path());
+                Patch::from_buffers(old, path, new, path, Some(&mut diff_options))?
+            }
+            None => match Patch::from_diff(&diff_result, delta_index)? {
+                Some(patch) => patch,
+                None => {
+                    log::debug!("delta {} has no patch, skipped", delta_index);
+                    continue;
+                }
+            },
+        };
+
+        let Some(file_path) = patch.delta().new_file().path_bytes().map(bytes_path) else {
+            log::debug!("delta {} has no new path, skipped", delta_index);
+            continue;
+        };
+        log::debug!("delta {:?} {}", patch.delta().status(), file_path.display());
+        let rules = &file_rules(repo, options.rules, &file_path, out);
+
+        // the working tree side of a diff has no blob yet
+        let new_content = || -> Result<Vec<u8>, RTrimError> {
+            if let Some((_, _, new)) = &utf16_sides {
+                return Ok(new.clone());
+            }
+            match repo.workdir().filter(|_| options.working_tree) {
+                Some(workdir) => Ok(fs::read(working_tree_path(workdir, &file_path))?),
+                None => Ok(repo.find_blob(patch.delta().new_file().id())?.content().to_vec()),
+            }
+        };
+
+        if merging && has_conflict_markers(&new_content()?) {
+            skip_conflict(&file_path, "it has conflict markers", &mut skipped, out);
+            continue;
+        }
+
+        if options.whole_file {
+            let content = new_content()?;
+            let mut findings = content_findings(&content, rules);
+            skip_protected(&file_path, &content, &mut findings, rules, &mut protected, out);
+            log::trace!("{}: flagged {} in the whole file", file_path.display(), findings.len());
+
+            if !findings.is_empty() {
+                result.insert(file_path, findings);
+            }
+            continue;
+        }
+
+        let mut added = AddedLines::of(&patch, &file_path, rules)?;
+        if added.needs_content(rules) {
+            // the regions are only known from the whole file
+            let content = new_content()?;
+            skip_protected(&file_path, &content, &mut added.findings, rules, &mut protected, out);
+            added.extend_from_content(&file_path, &content, rules);
+        }
+
+        if !added.findings.is_empty() {
+            result.insert(file_path, added.findings);
+        }
+    }
+
+    let files_scanned = files_scanned.saturating_sub(skipped.len());
+    skipped.extend(protected);
+
+    Ok(Scan {
+        files: result,
+        files_scanned,
+        skipped,
+    })
+}
+
+/// Returns the lines with trailing whitespaces of every file in the index and the number of scanned files,
+/// binary files, submodules and symlinks are skipped. With `include_untracked` the untracked files that
+/// are not ignored are scanned too.
+pub fn get_tracked_lines_with_trailing_spaces(
+    repo: &Repository,
+    options: &ScanOptions,
+    out: &Output,
+) -> Result<Scan, RTrimError> {
+    let mut result: StagedLines = BTreeMap::new();
+    let mut skipped: Vec<SkippedFile> = Vec::new();
+
+    let index = repo.index()?;
+    let pathspecs = Pathspecs::parse(options.path_filters)?;
+
+    let merging = is_merging(repo);
+    // the entries of the conflict stages, their paths have no stage 0 entry
+    let conflicted: BTreeSet<PathBuf> = index
+        .iter()
+        .filter(|entry| (entry.flags >> 12) & 0x3 != 0)
+        .map(|entry| bytes_path(&entry.path))
+        .filter(|path| pathspecs.matches(&path.to_string_lossy()))
+        .collect();
+    for path in &conflicted {
+        skip_conflict(path, "unresolved conflict", &mut skipped, out);
+    }
+
+    // regular files only, the file type bits of gitlinks and symlinks differ
+    let entries: Vec<_> = index
+        .iter()
+        .filter(|entry| entry.mode & 0o170000 == 0o100000)
+        .filter(|entry| (entry.flags >> 12) & 0x3 == 0)
+        .filter(|entry| pathspecs.matches(&String::from_utf8_lossy(&entry.path)))
+        .collect();
+
+    let mut progress = out.progress("scanning", entries.len());
+    let mut files_scanned = 0;
+
+    for entry in entries {
+        progress.tick();
+
+        let file_path = bytes_path(&entry.path);
+
+        if options.skip_large(&file_path, entry.file_size as u64, &mut skipped, out) {
+            continue;
+        }
+
+        let blob = repo.find_blob(entry.id)?;
+        if is_lfs_pointer(repo, &file_path, Some(blob.content())) {
+            out.verbose(format_args!("{}: skipped as Git LFS pointer", display_path(&file_path)));
+            continue;
+        }
+        let binary = blob.is_binary() && !options.is_utf16(blob.content());
+        if options.skip_binary(&file_path, binary, &mut skipped, out) {
+            continue;
+        }
+        let Some(content) = options.decode(&file_path, blob.content(), &mut skipped, out) else {
+            continue;
+        };
+
+        if merging && has_conflict_markers(&content) {
+            skip_conflict(&file_path, "it has conflict markers", &mut skipped, out);
+            continue;
+        }
+
+        files_scanned += 1;
+
+        let rules = file_rules(repo, options.rules, &file_path, out);
+        let mut findings = content_findings(&content, &rules);
+        skip_protected(&file_path, &content, &mut findings, &rules, &mut skipped, out);
+        if !findings.is_empty() {
+            log::trace!("{}: flagged {}", file_path.display(), findings.len());
+            result.insert(file_path, findings);
+        }
+    }
+
+    drop(progress);
+
+    if options.include_untracked {
+        files_scanned += scan_untracked(repo, options, &mut result, &mut skipped, out)?;
+    }
+
+    Ok(Scan {
+        files: result,
+        files_scanned,
+        skipped,
+    })
+}
+
+/// Adds the findings of the untracked files that are not ignored, returns the number of scanned files.
+fn scan_untracked(
+    repo: &Repository,
+    options: &ScanOptions,
+    result: &mut StagedLines,
+    skipped: &mut Vec<SkippedFile>,
+    out: &Output,
+) -> Result<usize, RTrimError> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(0);
+    };
+
+    let mut status_options = StatusOptions::new();
+    status_options.include_untracked(true).recurse_untracked_dirs(true);
+
+    let pathspecs = Pathspecs::parse(options.path_filters)?;
+
+    for path_filter in pathspecs.for_libgit2() {
+        status_options.pathspec(path_filter);
+    }
+
+    let mut files_scanned = 0;
+
+    for entry in repo.statuses(Some(&mut status_options))?.iter() {
+        if !entry.status().is_wt_new() {
+            continue;
+        }
+        let path = &bytes_path(entry.path_bytes());
+
+        if !pathspecs.matches(&path.to_string_lossy()) {
+            continue;
+        }
+
+        let file_path = workdir.join(path);
+        let metadata = fs::symlink_metadata(&file_path)?;
+        if metadata.file_type().is_symlink() {
+            out.verbose(format_args!("{}: skipped as symlink", display_path(path)));
+            continue;
+        }
+        if options.skip_large(path, metadata.len(), skipped, out) {
+            continue;
+        }
+
+        let content = fs::read(&file_path)?;
+        if is_lfs_pointer(repo, path, Some(&content)) {
+            out.verbose(format_args!("{}: skipped as Git LFS pointer", display_path(path)));
+            continue;
+        }
+        let binary = is_binary_unless_utf16(&content, options.rules.utf16);
+        if options.skip_binary(path, binary, skipped, out) {
+            continue;
+        }
+        let Some(content) = options.decode(path, &content, skipped, out) else {
+            continue;
+        };
+
+        files_scanned += 1;
+
+        let rules = file_rules(repo, options.rules, path, out);
+        let mut findings = content_findings(&content, &rules);
+        skip_protected(path, &content, &mut findings, &rules, skipped, out);
+        if !findings.is_empty() {
+            log::trace!("{}: flagged {}", path.display(), findings.len());
+            result.insert(path.clone(), findings);
+        }
+    }
+
+    Ok(files_scanned)
+}
@@ -0,0 +1,674 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::encoding::Utf16;
+use crate::rules::Rules;
+
+/// The UTF-8 byte order mark.
+pub const BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Splits `line` into its content and its line ending, which is `\r\n`, `\n` or empty for the last line.
+pub fn split_line_ending(line: &[u8]) -> (&[u8], &[u8]) {
+    if let Some(content) = line.strip_suffix(b"\r\n") {
+        (content, &line[content.len()..])
+    } else if let Some(content) = line.strip_suffix(b"\n") {
+        (content, &line[content.len()..])
+    } else {
+        (line, &[])
+    }
+}
+
+/// Removes trailing spaces and tabs.
+pub fn trim_end_bytes(content: &[u8]) -> &[u8] {
+    let end = content
+        .iter()
+        .rposition(|b| *b != b' ' && *b != b'\t')
+        .map_or(0, |i| i + 1);
+
+    &content[..end]
+}
+
+/// Trims every line of `input`, line endings and a missing final newline are kept byte-for-byte
+/// unless the rules convert them. Returns whether anything was trimmed.
+pub fn trim_all_lines<W: Write>(input: &[u8], rules: &Rules, writer: &mut W) -> Result<bool, std::io::Error> {
+    let mut trimmed = false;
+    // blank lines are held back until a line with text follows, at the end of the input they are dropped
+    let mut blank_lines: Vec<u8> = Vec::new();
+    let mut unterminated = false;
+    let (mut crlf, mut lf) = (0, 0);
+    let protected = rules.protected_lines(input);
+
+    for (line_no, line) in (1..).zip(input.split_inclusive(|b| *b == b'\n')) {
+        let (content, line_ending) = split_line_ending(line);
+        let new_content = match protected.binary_search(&line_no) {
+            Err(_) => rules.fix_line(content),
+            Ok(_) => Cow::Borrowed(content),
+        };
+
+        trimmed |= *new_content != *content;
+        let line_ending = match rules.eol {
+            Some(eol) if !line_ending.is_empty() => {
+                trimmed |= line_ending != eol.as_bytes();
+                eol.as_bytes()
+            }
+            _ => line_ending,
+        };
+        match line_ending {
+            b"\r\n" => crlf += 1,
+            b"\n" => lf += 1,
+            _ => {}
+        }
+
+        if rules.trim_eof_blank_lines && rules.is_blank(content) {
+            blank_lines.extend_from_slice(&new_content);
+            blank_lines.extend_from_slice(line_ending);
+            continue;
+        }
+
+        writer.write_all(&blank_lines)?;
+        blank_lines.clear();
+        writer.write_all(&new_content)?;
+        writer.write_all(line_ending)?;
+        unterminated = line_ending.is_empty();
+    }
+
+    if rules.ensure_final_newline && unterminated {
+        writer.write_all(rules.eol.unwrap_or_else(|| dominant_line_ending(crlf, lf)).as_bytes())?;
+        trimmed = true;
+    }
+
+    Ok(trimmed || !blank_lines.is_empty())
+}
+
+/// `LF` or `CRLF`, for messages.
+pub fn eol_name(eol: &str) -> &'static str {
+    if eol == "\r\n" {
+        "CRLF"
+    } else {
+        "LF"
+    }
+}
+
+/// CRLF if more lines end with it than with LF.
+pub fn dominant_line_ending(crlf: usize, lf: usize) -> &'static str {
+    if crlf > lf {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// Fixes the given lines (1-based, ascending) of `content`, everything else is kept byte-for-byte.
+pub fn trim_lines(content: &[u8], lines: &[u32], rules: &Rules) -> Vec<u8> {
+    let mut trimmed = Vec::with_capacity(content.len());
+
+    for (line_no, line) in (1..).zip(content.split_inclusive(|b| *b == b'\n')) {
+        let (line_content, line_ending) = split_line_ending(line);
+
+        if lines.binary_search(&line_no).is_ok() {
+            trimmed.extend_from_slice(&rules.fix_line(line_content));
+        } else {
+            trimmed.extend_from_slice(line_content);
+        }
+        trimmed.extend_from_slice(line_ending);
+    }
+
+    trimmed
+}
+
+/// The blank lines (1-based) removed when squeezing the runs of more than `max` blank lines, `blank` has an entry
+/// per line. Only the lines `removable` accepts are removed, the last ones of a run first.
+pub fn extra_blank_lines(blank: &[bool], max: u32, removable: impl Fn(u32) -> bool) -> Vec<u32> {
+    let mut extra: Vec<u32> = Vec::new();
+    let mut run: Vec<u32> = Vec::new();
+
+    // the line after the last one ends the last run
+    for line_no in 1..=blank.len() as u32 + 1 {
+        if blank.get(line_no as usize - 1) == Some(&true) {
+            run.push(line_no);
+            continue;
+        }
+
+        let excess = run.len().saturating_sub(max as usize);
+        let mut removed: Vec<u32> = run.iter().rev().copied().filter(|l| removable(*l)).take(excess).collect();
+        removed.reverse();
+        extra.extend(removed);
+        run.clear();
+    }
+
+    extra
+}
+
+/// What `rtrim_files` changed.
+#[derive(Default)]
+pub struct TrimStats {
+    /// Trimmed line numbers per modified file.
+    pub files: BTreeMap<PathBuf, Vec<u32>>,
+    /// Converted line endings per modified file, files without conversions are missing.
+    pub line_endings: BTreeMap<PathBuf, usize>,
+    pub bytes_removed: usize,
+}
+
+impl TrimStats {
+    pub fn files_modified(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn lines_trimmed(&self) -> usize {
+        self.files.values().map(Vec::len).sum()
+    }
+
+    /// Adds the changes of the files of `other`. The changes of a file in both are summed, e.g. of a file trimmed
+    /// in the index and in the working tree.
+    pub fn extend(&mut self, other: TrimStats) {
+        self.bytes_removed += other.bytes_removed;
+        for (file_name, lines) in other.files {
+            self.files.entry(file_name).or_default().extend(lines);
+        }
+        for (file_name, converted) in other.line_endings {
+            *self.line_endings.entry(file_name).or_default() += converted;
+        }
+    }
+
+    /// Adds the changes of a modified file.
+    pub fn record(&mut self, file_name: &Path, trimmed_file: &TrimmedFile) {
+        self.bytes_removed += trimmed_file.bytes_removed();
+        self.files.insert(file_name.to_path_buf(), trimmed_file.trimmed_lines().to_vec());
+
+        let converted = trimmed_file.line_endings_converted();
+        if converted > 0 {
+            self.line_endings.insert(file_name.to_path_buf(), converted);
+        }
+    }
+}
+
+/// A line of a file and the line ending it had.
+struct Line {
+    content: Vec<u8>,
+    /// The fixed content of a flagged line, if fixing changed it.
+    fixed: Option<Vec<u8>>,
+    /// Whether the content is empty or whitespaces only.
+    blank: bool,
+    /// `\r\n`, `\n` or empty for a last line without newline.
+    ending: &'static str,
+}
+
+/// `content` decoded to UTF-8 if it is UTF-16 with a BOM and `--utf16` is given, with its byte order.
+fn decode_utf16<'a>(content: &'a [u8], rules: &Rules) -> Result<(Cow<'a, [u8]>, Option<Utf16>), io::Error> {
+    match Utf16::detect(content).filter(|_| rules.utf16) {
+        Some(utf16) => match utf16.decode(content) {
+            Some(decoded) => Ok((Cow::Owned(decoded), Some(utf16))),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, format!("not valid {}", utf16.name()))),
+        },
+        None => Ok((Cow::Borrowed(content), None)),
+    }
+}
+
+/// The content of a file with the flagged lines trimmed in memory, nothing is written to disk.
+/// Line endings are kept as they were.
+pub struct TrimmedFile {
+    /// Whether the file starts with a UTF-8 BOM, it isn't part of the first line's content.
+    bom: bool,
+    /// The byte order of a UTF-16 file, its lines are held decoded to UTF-8 and encoded back when written.
+    utf16: Option<Utf16>,
+    lines: Vec<Line>,
+    /// Also contains the removed lines.
+    trimmed_lines: Vec<u32>,
+    /// The first of the blank lines removed from the end of the file.
+    removed_from: Option<u32>,
+    /// The blank lines removed from runs of blank lines, ascending.
+    removed_lines: Vec<u32>,
+    /// The line ending appended to the last line, which had none.
+    added_newline: Option<&'static str>,
+    /// The line ending every line is converted to.
+    eol: Option<&'static str>,
+}
+
+impl TrimmedFile {
+    /// Only trims the flagged lines, `flagged_lines` have to be in ascending order.
+    pub fn read(
+        path: &Path,
+        flagged_lines: impl IntoIterator<Item = u32>,
+        rules: &Rules,
+    ) -> Result<Self, std::io::Error> {
+        // the protected regions are only known from the whole file, UTF-16 is decoded as a whole
+        if rules.region.is_some() || rules.utf16 {
+            return Self::parse(&fs::read(path)?, flagged_lines, rules);
+        }
+
+        let mut flagged_lines = flagged_lines.into_iter().peekable();
+
+        Self::from_reader(BufReader::new(File::open(path)?), rules, |line_no| {
+            if flagged_lines.peek() == Some(&line_no) {
+                _ = flagged_lines.next();
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    /// Like `read` for content that isn't a file, e.g. a staged blob.
+    pub fn parse(
+        content: &[u8],
+        flagged_lines: impl IntoIterator<Item = u32>,
+        rules: &Rules,
+    ) -> Result<Self, std::io::Error> {
+        let (content, utf16) = decode_utf16(content, rules)?;
+        let mut flagged_lines = flagged_lines.into_iter().peekable();
+        let protected = rules.protected_lines(&content);
+
+        let mut trimmed_file = Self::from_reader(&content[..], rules, |line_no| {
+            if flagged_lines.peek() == Some(&line_no) {
+                _ = flagged_lines.next();
+                protected.binary_search(&line_no).is_err()
+            } else {
+                false
+            }
+        })?;
+        trimmed_file.utf16 = utf16;
+
+        Ok(trimmed_file)
+    }
+
+    /// Trims every line of the file outside of the protected regions,
+    /// with `trim_eof_blank_lines` the blank lines at its end are removed.
+    pub fn read_all(path: &Path, rules: &Rules) -> Result<Self, std::io::Error> {
+        let mut trimmed_file = if rules.region.is_some() || rules.utf16 {
+            let content = fs::read(path)?;
+            let (content, utf16) = decode_utf16(&content, rules)?;
+            let protected = rules.protected_lines(&content);
+            let mut trimmed_file =
+                Self::from_reader(&content[..], rules, |line_no| protected.binary_search(&line_no).is_err())?;
+            trimmed_file.utf16 = utf16;
+            trimmed_file
+        } else {
+            Self::from_reader(BufReader::new(File::open(path)?), rules, |_| true)?
+        };
+        if let Some(max) = rules.squeeze_blank_lines {
+            trimmed_file.squeeze_blank_lines(max);
+        }
+        if rules.trim_eof_blank_lines {
+            trimmed_file.remove_eof_blank_lines();
+        }
+        if rules.ensure_final_newline {
+            trimmed_file.add_final_newline();
+        }
+
+        Ok(trimmed_file)
+    }
+
+    /// `should_trim` is called once per line number in ascending order.
+    fn from_reader<R: BufRead>(
+        mut reader: R,
+        rules: &Rules,
+        mut should_trim: impl FnMut(u32) -> bool,
+    ) -> Result<Self, std::io::Error> {
+        let mut lines: Vec<Line> = Vec::new();
+        let mut trimmed_lines: Vec<u32> = Vec::new();
+        let mut raw_line: Vec<u8> = Vec::new();
+        let mut line_no: u32 = 0;
+        let mut bom = false;
+
+        loop {
+            raw_line.clear();
+            if reader.read_until(b'\n', &mut raw_line)? == 0 {
+                break;
+            }
+            line_no += 1;
+
+            let (mut content, ending) = split_line_ending(&raw_line);
+            if line_no == 1 {
+                if let Some(rest) = content.strip_prefix(BOM) {
+                    content = rest;
+                    bom = true;
+                }
+            }
+            let ending = match ending {
+                b"\r\n" => "\r\n",
+                b"\n" => "\n",
+                _ => "",
+            };
+            let fixed = match should_trim(line_no) {
+                true => Some(rules.fix_line(content)).filter(|fixed| **fixed != *content).map(Cow::into_owned),
+                false => None,
+            };
+            if fixed.is_some() {
+                trimmed_lines.push(line_no);
+            }
+
+            lines.push(Line { content: content.to_vec(), fixed, blank: rules.is_blank(content), ending });
+        }
+
+        Ok(TrimmedFile {
+            bom,
+            utf16: None,
+            lines,
+            trimmed_lines,
+            removed_from: None,
+            removed_lines: Vec::new(),
+            added_newline: None,
+            eol: None,
+        })
+    }
+
+    /// Terminates a last line without newline with the line ending most lines use.
+    pub fn add_final_newline(&mut self) {
+        if self.is_removed(self.lines.len() as u32) || self.ends_with_newline() {
+            return;
+        }
+
+        let crlf = self.lines.iter().filter(|line| line.ending == "\r\n").count();
+        let lf = self.lines.iter().filter(|line| line.ending == "\n").count();
+        self.added_newline = Some(self.eol.unwrap_or_else(|| dominant_line_ending(crlf, lf)));
+
+        let last_line = self.lines.len() as u32;
+        if !self.is_trimmed(last_line) {
+            self.trimmed_lines.push(last_line);
+        }
+    }
+
+    /// Converts every line ending to `eol`, including a final newline that is added.
+    pub fn convert_line_endings(&mut self, eol: &'static str) {
+        self.eol = Some(eol);
+        if self.added_newline.is_some() {
+            self.added_newline = Some(eol);
+        }
+    }
+
+    /// Whether the line ending of the line (1-based) is converted, the ones of removed lines aren't.
+    pub fn converts_line_ending(&self, line_no: u32) -> bool {
+        let ending = self.lines[line_no as usize - 1].ending;
+
+        self.eol.is_some_and(|eol| !self.is_removed(line_no) && !ending.is_empty() && ending != eol)
+    }
+
+    /// The number of line endings that are converted.
+    pub fn line_endings_converted(&self) -> usize {
+        (1..=self.lines.len() as u32).filter(|line_no| self.converts_line_ending(*line_no)).count()
+    }
+
+    /// The line ending every line is converted to.
+    pub fn eol(&self) -> Option<&'static str> {
+        self.eol
+    }
+
+    /// Whether writing the file changes it.
+    pub fn is_modified(&self) -> bool {
+        !self.trimmed_lines.is_empty() || self.line_endings_converted() > 0
+    }
+
+    /// Removes the run of blank lines at the end of the file, the line before it keeps its newline.
+    pub fn remove_eof_blank_lines(&mut self) {
+        let text_lines = self.lines.iter().rposition(|line| !line.blank).map_or(0, |i| i + 1);
+        if text_lines == self.lines.len() {
+            return;
+        }
+
+        let start = text_lines as u32 + 1;
+        self.trimmed_lines.retain(|line_no| *line_no < start);
+        self.trimmed_lines.extend(start..=self.lines.len() as u32);
+        self.removed_from = Some(start);
+    }
+
+    /// Leaves the line `line_no` (1-based) as it was, undoing its fix.
+    pub fn keep_line(&mut self, line_no: u32) {
+        if let Some(line) = self.lines.get_mut((line_no as usize).wrapping_sub(1)) {
+            line.fixed = None;
+        }
+        self.trimmed_lines.retain(|l| *l != line_no);
+    }
+
+    /// Removes the given blank lines (1-based), the line numbers of the other lines stay the same.
+    pub fn remove_lines(&mut self, lines: &[u32]) {
+        self.removed_lines.extend(lines);
+        self.removed_lines.sort_unstable();
+        self.removed_lines.dedup();
+        self.trimmed_lines.extend(lines);
+        self.trimmed_lines.sort_unstable();
+        self.trimmed_lines.dedup();
+    }
+
+    /// Squeezes every run of more than `max` blank lines down to `max` lines.
+    pub fn squeeze_blank_lines(&mut self, max: u32) {
+        let blank: Vec<bool> = self.lines.iter().map(|line| line.blank).collect();
+
+        self.remove_lines(&extra_blank_lines(&blank, max, |_| true));
+    }
+
+    /// Line numbers (1-based) that are fixed or removed.
+    pub fn trimmed_lines(&self) -> &[u32] {
+        &self.trimmed_lines
+    }
+
+    pub fn bytes_removed(&self) -> usize {
+        self.trimmed_lines
+            .iter()
+            .map(|&l| match self.is_removed(l) {
+                true => self.original_line(l).len() + self.lines[l as usize - 1].ending.len(),
+                false => self.original_line(l).len().saturating_sub(self.new_line(l).len()),
+            })
+            .sum()
+    }
+
+    /// The blank lines removed from runs of blank lines.
+    pub fn removed_lines(&self) -> &[u32] {
+        &self.removed_lines
+    }
+
+    /// The first of the removed blank lines at the end of the file.
+    pub fn removed_from(&self) -> Option<u32> {
+        self.removed_from
+    }
+
+    /// Whether the line (1-based) is one of the removed blank lines.
+    pub fn is_removed(&self, line_no: u32) -> bool {
+        self.removed_from.is_some_and(|start| line_no >= start) || self.removed_lines.binary_search(&line_no).is_ok()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The BOM the file starts with, empty if it has none.
+    pub fn bom(&self) -> &'static str {
+        if self.bom {
+            "\u{feff}"
+        } else {
+            ""
+        }
+    }
+
+    /// Whether the last line ends with a newline, also true for an empty file.
+    pub fn ends_with_newline(&self) -> bool {
+        self.lines.last().is_none_or(|line| !line.ending.is_empty())
+    }
+
+    /// Whether a line ending is appended to the last line.
+    pub fn adds_final_newline(&self) -> bool {
+        self.added_newline.is_some()
+    }
+
+    pub fn is_trimmed(&self, line_no: u32) -> bool {
+        self.trimmed_lines.binary_search(&line_no).is_ok()
+    }
+
+    /// The content of the line (1-based) before trimming.
+    pub fn original_line(&self, line_no: u32) -> &[u8] {
+        &self.lines[line_no as usize - 1].content
+    }
+
+    /// The content of the line (1-based) after fixing, empty for a removed line.
+    pub fn new_line(&self, line_no: u32) -> &[u8] {
+        let line = &self.lines[line_no as usize - 1];
+
+        match &line.fixed {
+            Some(fixed) => fixed,
+            None if self.is_removed(line_no) => &[],
+            None => &line.content,
+        }
+    }
+
+    /// The line ending of the line (1-based) before the changes, empty for a last line without newline.
+    pub fn original_ending(&self, line_no: u32) -> &'static str {
+        self.lines[line_no as usize - 1].ending
+    }
+
+    /// The line ending of the line (1-based) after the changes, empty for a removed line.
+    pub fn new_ending(&self, line_no: u32) -> &'static str {
+        let ending = self.original_ending(line_no);
+
+        match self.eol {
+            _ if self.is_removed(line_no) => "",
+            Some(eol) if !ending.is_empty() => eol,
+            _ if ending.is_empty() && line_no as usize == self.lines.len() => self.added_newline.unwrap_or(""),
+            _ => ending,
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        if let Some(utf16) = self.utf16 {
+            let mut content = Vec::new();
+            self.write_utf8_to(&mut content)?;
+            return writer.write_all(&utf16.encode(&content));
+        }
+
+        self.write_utf8_to(writer)
+    }
+
+    fn write_utf8_to<W: Write>(&self, writer: &mut W) -> Result<(), std::io::Error> {
+        if self.bom {
+            writer.write_all(BOM)?;
+        }
+
+        for (line_no, line) in (1..).zip(&self.lines) {
+            if self.is_removed(line_no) {
+                continue;
+            }
+            writer.write_all(self.new_line(line_no))?;
+            match self.eol {
+                Some(eol) if !line.ending.is_empty() => writer.write_all(eol.as_bytes())?,
+                _ => writer.write_all(line.ending.as_bytes())?,
+            }
+        }
+        if let Some(ending) = self.added_newline {
+            writer.write_all(ending.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trim_all(content: &[u8], rules: &Rules) -> Vec<u8> {
+        let mut trimmed = Vec::new();
+        _ = trim_all_lines(content, rules, &mut trimmed).unwrap();
+        trimmed
+    }
+
+    fn trim_flagged(content: &[u8], lines: &[u32], rules: &Rules) -> Vec<u8> {
+        let mut trimmed = Vec::new();
+        TrimmedFile::parse(content, lines.iter().copied(), rules).unwrap().write_to(&mut trimmed).unwrap();
+        trimmed
+    }
+
+    #[test]
+    fn crlf_line_endings_are_kept() {
+        let rules = Rules::default();
+
+        assert_eq!(trim_all(b"a  \r\nb\t\r\nc\r\n", &rules), b"a\r\nb\r\nc\r\n");
+        assert_eq!(trim_flagged(b"a  \r\nb \r\n", &[1], &rules), b"a\r\nb \r\n");
+    }
+
+    #[test]
+    fn mixed_line_endings_are_kept() {
+        let rules = Rules::default();
+
+        assert_eq!(trim_all(b"a \r\nb \nc\r\n", &rules), b"a\r\nb\nc\r\n");
+        assert_eq!(trim_flagged(b"a \r\nb \nc\r\n", &[1, 2], &rules), b"a\r\nb\nc\r\n");
+    }
+
+    #[test]
+    fn missing_final_newline_is_not_added() {
+        let rules = Rules::default();
+
+        assert_eq!(trim_all(b"a\nb  ", &rules), b"a\nb");
+        assert_eq!(trim_flagged(b"a\nb  ", &[2], &rules), b"a\nb");
+        assert!(!TrimmedFile::parse(b"a\nb  ", [2], &rules).unwrap().adds_final_newline());
+    }
+
+    #[test]
+    fn final_newline_uses_the_dominant_line_ending() {
+        let rules = Rules { ensure_final_newline: true, ..Rules::default() };
+
+        assert_eq!(trim_all(b"a\r\nb\r\nc\nd", &rules), b"a\r\nb\r\nc\nd\r\n");
+
+        let mut trimmed_file = TrimmedFile::parse(b"a\r\nb", [], &rules).unwrap();
+        trimmed_file.add_final_newline();
+        let mut trimmed = Vec::new();
+        trimmed_file.write_to(&mut trimmed).unwrap();
+        assert_eq!(trimmed, b"a\r\nb\r\n");
+    }
+
+    #[test]
+    fn bom_is_kept() {
+        let rules = Rules::default();
+
+        assert_eq!(trim_all(b"\xef\xbb\xbfa  \nb\n", &rules), b"\xef\xbb\xbfa\nb\n");
+        assert_eq!(trim_flagged(b"\xef\xbb\xbf  \n", &[1], &rules), b"\xef\xbb\xbf\n");
+
+        let trimmed_file = TrimmedFile::parse(b"\xef\xbb\xbfa \n", [1], &rules).unwrap();
+        assert_eq!(trimmed_file.original_line(1), b"a ");
+        assert_eq!(trimmed_file.new_line(1), b"a");
+    }
+
+    #[test]
+    fn utf16_is_encoded_back() {
+        let rules = Rules { utf16: true, ..Rules::default() };
+
+        for utf16 in [Utf16::Le, Utf16::Be] {
+            let content = utf16.encode(b"a  \r\nb\t\r\n");
+            let trimmed_file = TrimmedFile::parse(&content, [1, 2], &rules).unwrap();
+            assert_eq!(trimmed_file.new_line(1), b"a");
+
+            let mut trimmed = Vec::new();
+            trimmed_file.write_to(&mut trimmed).unwrap();
+            assert_eq!(trimmed, utf16.encode(b"a\r\nb\r\n"));
+        }
+    }
+
+    #[test]
+    fn utf16_is_left_alone_without_the_rule() {
+        let content = Utf16::Le.encode(b"a  \n");
+
+        assert_eq!(trim_flagged(&content, &[1], &Rules::default()), content);
+    }
+
+    #[test]
+    fn eol_converts_every_terminated_line() {
+        let rules = Rules { eol: Some("\n"), ..Rules::default() };
+        let mut trimmed_file = TrimmedFile::parse(b"a\r\nb\nc", [], &rules).unwrap();
+        trimmed_file.convert_line_endings("\n");
+
+        let mut trimmed = Vec::new();
+        trimmed_file.write_to(&mut trimmed).unwrap();
+        assert_eq!(trimmed, b"a\nb\nc");
+        assert_eq!(trimmed_file.line_endings_converted(), 1);
+        assert!(trimmed_file.converts_line_ending(1));
+        assert!(!trimmed_file.converts_line_ending(3));
+    }
+
+    #[test]
+    fn eof_blank_lines_are_removed() {
+        let rules = Rules { trim_eof_blank_lines: true, ..Rules::default() };
+
+        assert_eq!(trim_all(b"a\n\n \n\t\n", &rules), b"a\n");
+        assert_eq!(trim_all(b"a\n\nb\n", &rules), b"a\n\nb\n");
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+use git2::{DiffOptions, Index, Repository};
+use notify::{RecursiveMode, Watcher};
+
+use crate::cli::Cli;
+use crate::editorconfig;
+use crate::error::RTrimError;
+use crate::output::{display_path, Output};
+use crate::report::plural;
+use crate::rules::Rules;
+use crate::scan::is_binary_unless_utf16;
+use crate::trim::TrimmedFile;
+use crate::fix::{format_line_numbers, is_symlink, replace_file};
+use crate::{effective_config, filter, Outcome};
+
+/// What the watch loop waits for.
+enum Message {
+    Event(notify::Result<notify::Event>),
+    Stop,
+}
+
+/// Line numbers of the working tree file that were added compared to the index.
+fn added_lines(repo: &Repository, index: &Index, path: &Path) -> Result<Vec<u32>, git2::Error> {
+    let mut options = DiffOptions::new();
+    options.pathspec(path).disable_pathspec_match(true).context_lines(0);
+
+    let diff = repo.diff_index_to_workdir(Some(index), Some(&mut options))?;
+    let mut lines = Vec::new();
+
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |_, _, line| {
+            if line.origin() == '+' {
+                lines.extend(line.new_lineno());
+            }
+            true
+        }),
+    )?;
+
+    lines.sort_unstable();
+    Ok(lines)
+}
+
+/// Trims one changed file, `written` remembers the modification times of rtrim's own writes so they
+/// don't trigger another round.
+fn trim_changed_file(
+    cli: &Cli,
+    repo: &Repository,
+    workdir: &Path,
+    path: &Path,
+    written: &mut HashMap<PathBuf, SystemTime>,
+    out: &Output,
+) -> Result<(), RTrimError> {
+    let Ok(relative) = path.strip_prefix(workdir) else {
+        return Ok(());
+    };
+    if relative.components().any(|c| c.as_os_str() == ".git") {
+        return Ok(());
+    }
+
+    if is_symlink(path) {
+        out.verbose(format_args!("{}: skipped, it is a symlink", display_path(relative)));
+        return Ok(());
+    }
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        // deleted or renamed away in the meantime
+        return Ok(());
+    };
+    if !metadata.is_file() {
+        return Ok(());
+    }
+    let modified = metadata.modified().ok();
+    if modified.is_some() && written.get(path) == modified.as_ref() {
+        return Ok(());
+    }
+
+    let file_name = display_path(relative);
+    let name = relative.to_string_lossy();
+
+    let mut index = repo.index()?;
+    index.read(false)?;
+
+    if (1..=3).any(|stage| index.get_path(relative, stage).is_some()) {
+        out.verbose(format_args!("{}: skipped, unresolved conflict", file_name));
+        return Ok(());
+    }
+    if index.get_path(relative, 0).is_none() {
+        return Ok(());
+    }
+
+    if let Some((_, description)) = filter::skip_reason(&cli.file_filters, &name) {
+        out.verbose(format_args!("{}: skipped {}", file_name, description));
+        return Ok(());
+    }
+    if cli.backup_suffix().is_some_and(|suffix| name.ends_with(suffix)) {
+        return Ok(());
+    }
+    if cli.editorconfig && editorconfig::properties(workdir, relative).trim_trailing_whitespace == Some(false) {
+        out.verbose(format_args!("{}: skipped by .editorconfig (trim_trailing_whitespace = false)", file_name));
+        return Ok(());
+    }
+    if cli.max_file_size.is_some_and(|size| size > 0 && metadata.len() > size) {
+        out.verbose(format_args!("{}: skipped by size", file_name));
+        return Ok(());
+    }
+    if is_binary_unless_utf16(&fs::read(path)?, cli.utf16) {
+        return Ok(());
+    }
+
+    let rules = Rules::from_cli(cli).for_file(workdir, relative);
+    let trimmed_file = if cli.whole_file {
+        TrimmedFile::read_all(path, &rules)?
+    } else {
+        TrimmedFile::read(path, added_lines(repo, &index, relative)?, &rules)?
+    };
+
+    let trimmed_lines = trimmed_file.trimmed_lines();
+    if trimmed_lines.is_empty() {
+        return Ok(());
+    }
+
+    replace_file(path, &trimmed_file, cli.backup_suffix())?;
+    if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+        written.insert(path.to_path_buf(), modified);
+    }
+
+    out.info(format_args!(
+        "rtrim: {}: trimmed {} ({})",
+        file_name,
+        plural(trimmed_lines.len(), "line"),
+        format_line_numbers(trimmed_lines)
+    ));
+
+    Ok(())
+}
+
+/// `watch` subcommand: trims tracked files as they are written until Ctrl-C.
+pub fn run_watch(cli: &Cli, debounce: u64, out: &Output) -> Result<Outcome, RTrimError> {
+    let repo = Repository::discover(std::env::current_dir()?)?;
+    let Some(workdir) = repo.workdir().map(Path::to_path_buf) else {
+        return Err(RTrimError::Config(String::from("watch needs a repository with a working tree")));
+    };
+
+    let mut cli = cli.clone();
+    effective_config(&cli, &repo, &workdir, out)?.apply(&mut cli)?;
+
+    let (sender, receiver) = mpsc::channel();
+
+    let event_sender = sender.clone();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        _ = event_sender.send(Message::Event(event));
+    })
+    .map_err(io::Error::other)?;
+    watcher.watch(&workdir, RecursiveMode::Recursive).map_err(io::Error::other)?;
+
+    // files are only written between two messages, so stopping never leaves a temp file behind
+    ctrlc::set_handler(move || {
+        _ = sender.send(Message::Stop);
+    })
+    .map_err(io::Error::other)?;
+
+    out.info(format_args!("rtrim: watching {}, press Ctrl-C to stop", workdir.display()));
+
+    let debounce = Duration::from_millis(debounce);
+    let mut written: HashMap<PathBuf, SystemTime> = HashMap::new();
+    let mut pending: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut stop = false;
+
+    while !stop {
+        let message = if pending.is_empty() {
+            receiver.recv().map_err(io::Error::other)?
+        } else {
+            match receiver.recv_timeout(debounce) {
+                Ok(message) => message,
+                Err(_) => {
+                    for path in std::mem::take(&mut pending) {
+                        if let Err(e) = trim_changed_file(&cli, &repo, &workdir, &path, &mut written, out) {
+                            out.error(format_args!("{}: {}", path.display(), e));
+                        }
+                    }
+                    continue;
+                }
+            }
+        };
+
+        match message {
+            Message::Event(Ok(event)) if event.kind.is_create() || event.kind.is_modify() => {
+                pending.extend(event.paths);
+            }
+            Message::Event(Ok(_)) => {}
+            Message::Event(Err(e)) => out.warning(format_args!("watch error: {}", e)),
+            Message::Stop => stop = true,
+        }
+    }
+
+    Ok(Outcome::Clean)
+}
@@ -0,0 +1,140 @@
+//! Fixture repositories built with the git CLI, rtrim runs as a separate process in them.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+
+use tempfile::TempDir;
+
+/// A repository in a temporary directory, with its own home so that no global git config leaks in.
+pub struct Repo {
+    dir: TempDir,
+    root: PathBuf,
+}
+
+impl Repo {
+    pub fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("repo");
+        fs::create_dir_all(dir.path().join("home")).unwrap();
+        fs::create_dir_all(&root).unwrap();
+
+        let repo = Repo { dir, root };
+        repo.git(&["-c", "init.defaultBranch=main", "init", "-q"]);
+        repo
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// A directory next to the repository, e.g. for a linked worktree.
+    pub fn sibling(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    pub fn git(&self, args: &[&str]) -> String {
+        self.git_in(&self.root, args)
+    }
+
+    pub fn git_in(&self, dir: &Path, args: &[&str]) -> String {
+        self.git_with(dir, args, &[])
+    }
+
+    /// Runs git without asserting that it succeeds, e.g. a commit that a hook rejects.
+    pub fn git_output(&self, args: &[&str]) -> Output {
+        self.command("git", &self.root).args(args).output().unwrap()
+    }
+
+    pub fn git_with(&self, dir: &Path, args: &[&str], env: &[(&str, &Path)]) -> String {
+        let mut command = self.command("git", dir);
+        command.args(args);
+        for (name, value) in env {
+            command.env(name, value);
+        }
+        let output = command.output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) {
+        let path = self.root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, content).unwrap();
+    }
+
+    pub fn read(&self, path: impl AsRef<Path>) -> Vec<u8> {
+        fs::read(self.root.join(path)).unwrap()
+    }
+
+    /// The staged content of `path`.
+    pub fn staged(&self, path: &str) -> Vec<u8> {
+        let output = self.command("git", &self.root).args(["show", &format!(":{}", path)]).output().unwrap();
+        assert!(output.status.success(), "{}: not staged", path);
+
+        output.stdout
+    }
+
+    pub fn commit_all(&self) {
+        self.git(&["add", "-A"]);
+        self.git(&["commit", "-q", "-m", "commit"]);
+    }
+
+    pub fn rtrim(&self, args: &[&str]) -> Output {
+        self.rtrim_in(&self.root, args)
+    }
+
+    pub fn rtrim_in(&self, dir: &Path, args: &[&str]) -> Output {
+        self.rtrim_with(dir, args, &[])
+    }
+
+    pub fn rtrim_with(&self, dir: &Path, args: &[&str], env: &[(&str, &Path)]) -> Output {
+        let mut command = self.command(env!("CARGO_BIN_EXE_rtrim"), dir);
+        command.args(["--color", "never", "--no-progress"]).args(args);
+        for (name, value) in env {
+            command.env(name, value);
+        }
+
+        command.output().unwrap()
+    }
+
+    /// Starts rtrim without waiting for it, e.g. in watch mode. stderr is piped.
+    pub fn spawn_rtrim(&self, args: &[&str]) -> Child {
+        self.command(env!("CARGO_BIN_EXE_rtrim"), &self.root)
+            .args(["--color", "never", "--no-progress"])
+            .args(args)
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    /// A clean environment, CI variables would turn on check mode and `RTRIM_*` variables change the defaults.
+    fn command(&self, program: &str, dir: &Path) -> Command {
+        let mut command = Command::new(program);
+        command
+            .current_dir(dir)
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .env("HOME", self.dir.path().join("home"))
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_AUTHOR_NAME", "rtrim")
+            .env("GIT_AUTHOR_EMAIL", "rtrim@example.com")
+            .env("GIT_COMMITTER_NAME", "rtrim")
+            .env("GIT_COMMITTER_EMAIL", "rtrim@example.com");
+
+        command
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::{stderr, Repo};
+
+#[test]
+fn installed_hook_trims_and_commits() {
+    let repo = Repo::new();
+    let install = repo.rtrim(&["hook", "install"]);
+    assert_eq!(install.status.code(), Some(0), "{}", stderr(&install));
+
+    repo.write("a.txt", "a \nb\t\n");
+    repo.git(&["add", "a.txt"]);
+    repo.git(&["commit", "-q", "-m", "commit"]);
+
+    assert_eq!(repo.git(&["show", "HEAD:a.txt"]), "a\nb\n");
+    assert_eq!(repo.read("a.txt"), b"a\nb\n");
+    assert_eq!(repo.git(&["status", "--porcelain"]), "");
+}
+
+#[test]
+fn installed_hook_aborts_on_errors() {
+    let repo = Repo::new();
+    let install = repo.rtrim(&["hook", "install"]);
+    assert_eq!(install.status.code(), Some(0), "{}", stderr(&install));
+    repo.write(".rtrim.toml", "not toml = = =\n");
+
+    repo.write("a.txt", "a\n");
+    repo.git(&["add", "a.txt"]);
+    let commit = repo.git_output(&["commit", "-q", "-m", "commit"]);
+
+    assert!(!commit.status.success());
+    assert!(stderr(&commit).contains(".rtrim.toml"), "{}", stderr(&commit));
+    assert!(repo.git_output(&["rev-parse", "--verify", "-q", "HEAD"]).stdout.is_empty());
+}
+
+#[test]
+fn status_shows_the_command() {
+    let repo = Repo::new();
+    _ = repo.rtrim(&["hook", "install"]);
+
+    let status = repo.rtrim(&["hook", "status"]);
+
+    let status = String::from_utf8_lossy(&status.stdout);
+    assert!(status.trim_end().ends_with(&format!("runs '{}'", env!("CARGO_BIN_EXE_rtrim"))), "{}", status);
+}
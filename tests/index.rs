@@ -0,0 +1,99 @@
+mod common;
+
+use common::{stderr, Repo};
+
+#[test]
+fn linked_worktrees_are_trimmed() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    repo.commit_all();
+    let worktree = repo.sibling("worktree");
+    repo.git(&["worktree", "add", "-q", "-b", "feature", worktree.to_str().unwrap()]);
+    std::fs::write(worktree.join("a.txt"), "a\nb \n").unwrap();
+    repo.git_in(&worktree, &["add", "a.txt"]);
+
+    let output = repo.rtrim_in(&worktree, &[]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(std::fs::read(worktree.join("a.txt")).unwrap(), b"a\nb\n");
+    assert_eq!(repo.git_in(&worktree, &["show", ":a.txt"]), "a\nb\n");
+    assert_eq!(repo.read("a.txt"), b"a\n");
+}
+
+#[test]
+fn git_index_file_is_honored() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    repo.commit_all();
+    let index = repo.sibling("index");
+    std::fs::copy(repo.path().join(".git/index"), &index).unwrap();
+    repo.write("a.txt", "a\nb \n");
+
+    let env = [("GIT_INDEX_FILE", index.as_path())];
+    repo.git_with(repo.path(), &["add", "a.txt"], &env);
+
+    let output = repo.rtrim_with(repo.path(), &["--index-only"], &env);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.git_with(repo.path(), &["show", ":a.txt"], &env), "a\nb\n");
+    assert_eq!(repo.staged("a.txt"), b"a\n");
+    assert_eq!(repo.read("a.txt"), b"a\nb \n");
+}
+
+#[test]
+fn skip_worktree_files_are_trimmed_in_the_index_only() {
+    for flag in ["--skip-worktree", "--assume-unchanged"] {
+        let repo = Repo::new();
+        repo.write("a.txt", "a\n");
+        repo.commit_all();
+        repo.write("a.txt", "a\nb \n");
+        repo.git(&["add", "a.txt"]);
+        repo.git(&["update-index", flag, "a.txt"]);
+
+        let output = repo.rtrim(&[]);
+
+        assert_eq!(output.status.code(), Some(1), "{}: {}", flag, stderr(&output));
+        assert_eq!(repo.staged("a.txt"), b"a\nb\n", "{}", flag);
+        assert_eq!(repo.read("a.txt"), b"a\nb \n", "{}", flag);
+    }
+}
+
+#[test]
+fn lfs_pointers_are_skipped() {
+    let repo = Repo::new();
+    let pointer = "version https://git-lfs.github.com/spec/v1\noid sha256:0123 \nsize 12\n";
+    repo.write("large.bin", pointer);
+    repo.git(&["add", "large.bin"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(repo.staged("large.bin"), pointer.as_bytes());
+}
+
+#[test]
+fn intent_to_add_files_are_skipped() {
+    let repo = Repo::new();
+    repo.write("new.txt", "a \n");
+    repo.git(&["add", "-N", "new.txt"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(repo.read("new.txt"), b"a \n");
+}
+
+#[test]
+fn intent_to_add_files_stay_unstaged() {
+    let repo = Repo::new();
+    repo.write("new.txt", "a \n");
+    repo.git(&["add", "-N", "new.txt"]);
+
+    let output = repo.rtrim(&["--include-intent-to-add"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.read("new.txt"), b"a\n");
+    assert_eq!(repo.staged("new.txt"), b"");
+    assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "");
+    assert_eq!(repo.git(&["diff", "--name-only"]), "new.txt\n");
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use common::{stderr, stdout, Repo};
+use serde_json::Value;
+
+fn staged_repo() -> Repo {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\nb  \n");
+    repo.git(&["add", "a.txt"]);
+    repo
+}
+
+fn json(output: &std::process::Output) -> Value {
+    serde_json::from_slice(&output.stdout).unwrap_or_else(|e| panic!("{}: {}", e, stdout(output)))
+}
+
+#[test]
+fn json_report() {
+    let repo = staged_repo();
+
+    let output = repo.rtrim(&["--check", "--format", "json"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let report = json(&output);
+    assert_eq!(report["files"][0]["path"], "a.txt");
+    assert_eq!(report["files"][0]["findings"][0]["line"], 2);
+    assert_eq!(report["files"][0]["findings"][0]["column"], 2);
+    assert_eq!(report["files"][0]["findings"][0]["end_column"], 4);
+}
+
+/// Every property the SARIF 2.1.0 schema requires of the objects rtrim writes, with the values it constrains.
+#[test]
+fn sarif_report() {
+    let repo = Repo::new();
+    repo.write("dir/caf\u{e9} 1.txt", "a\nb  \n");
+    repo.write("tabs.txt", "\ta\n");
+    repo.git(&["add", "-A"]);
+
+    let output = repo.rtrim(&["--check", "--tab-in-indent", "--format", "sarif"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let sarif = json(&output);
+    assert_eq!(sarif["$schema"], "https://json.schemastore.org/sarif-2.1.0.json");
+    assert_eq!(sarif["version"], "2.1.0");
+    let runs = sarif["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+
+    let driver = &runs[0]["tool"]["driver"];
+    assert_eq!(driver["name"], "rtrim");
+    assert!(driver["version"].is_string());
+    let rules = driver["rules"].as_array().unwrap();
+    assert!(!rules.is_empty());
+    for rule in rules {
+        assert!(rule["id"].as_str().is_some_and(|id| !id.is_empty()), "{}", rule);
+        assert!(rule["name"].is_string(), "{}", rule);
+        assert!(rule["shortDescription"]["text"].is_string(), "{}", rule);
+        let level = rule["defaultConfiguration"]["level"].as_str().unwrap();
+        assert!(["none", "note", "warning", "error"].contains(&level), "{}", rule);
+    }
+    let rule_ids: Vec<&str> = rules.iter().map(|rule| rule["id"].as_str().unwrap()).collect();
+    let mut unique = rule_ids.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), rule_ids.len(), "rule ids are unique");
+
+    let base = runs[0]["originalUriBaseIds"]["SRCROOT"]["uri"].as_str().unwrap();
+    assert!(base.starts_with("file:///") && base.ends_with('/'), "{}", base);
+
+    let results = runs[0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    for result in results {
+        let index = result["ruleIndex"].as_u64().unwrap() as usize;
+        assert_eq!(result["ruleId"], rules[index]["id"]);
+        assert_eq!(result["level"], "error");
+        assert!(result["message"]["text"].as_str().is_some_and(|text| !text.is_empty()));
+
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uriBaseId"], "SRCROOT");
+        let region = &location["region"];
+        for property in ["startLine", "startColumn", "endColumn"] {
+            assert!(region[property].as_u64().is_some_and(|value| value >= 1), "{}: {}", property, region);
+        }
+        assert!(region["endColumn"].as_u64() > region["startColumn"].as_u64());
+    }
+
+    let trailing = &results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(results[0]["ruleId"], "trailing-whitespace");
+    assert_eq!(trailing["artifactLocation"]["uri"], "dir/caf%C3%A9%201.txt");
+    assert_eq!(trailing["region"]["startLine"], 2);
+    assert_eq!(trailing["region"]["startColumn"], 2);
+    assert_eq!(trailing["region"]["endColumn"], 4);
+
+    let indent = &results[1]["locations"][0]["physicalLocation"];
+    assert_eq!(results[1]["ruleId"], "tab-in-indent");
+    assert_eq!(indent["artifactLocation"]["uri"], "tabs.txt");
+    assert_eq!(indent["region"]["startLine"], 1);
+    assert_eq!(indent["region"]["startColumn"], 1);
+    assert_eq!(indent["region"]["endColumn"], 2);
+}
+
+#[test]
+fn rdjson_suggestions_remove_the_trimmed_whitespaces() {
+    let repo = staged_repo();
+
+    let output = repo.rtrim(&["--check", "--format", "rdjson"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let rdjson = json(&output);
+    let diagnostic = &rdjson["diagnostics"][0];
+    assert_eq!(diagnostic["location"]["path"], "a.txt");
+    let suggestion = &diagnostic["suggestions"][0];
+    assert_eq!(suggestion["text"], "");
+    assert_eq!(suggestion["range"]["start"]["column"], 2);
+    assert_eq!(suggestion["range"]["end"]["column"], 4);
+}
+
+#[test]
+fn diff_applies_to_the_index() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\r\nb  \r\nc \t");
+    repo.git(&["add", "a.txt"]);
+
+    let output = repo.rtrim(&["--diff"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let patch = repo.sibling("trim.patch");
+    std::fs::write(&patch, &output.stdout).unwrap();
+    repo.git(&["apply", "--cached", patch.to_str().unwrap()]);
+    assert_eq!(repo.staged("a.txt"), b"a\r\nb\r\nc");
+    assert_eq!(repo.read("a.txt"), b"a\r\nb  \r\nc \t");
+}
+
+#[test]
+fn clean_check_prints_no_findings() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    repo.git(&["add", "a.txt"]);
+
+    let output = repo.rtrim(&["--check", "--format", "json"]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(json(&output)["files"], Value::Array(Vec::new()));
+}
+
+#[test]
+fn list_files_prints_only_paths() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a \n");
+    repo.write("b.txt", "b \n");
+    repo.write("c.txt", "c\n");
+    repo.git(&["add", "-A"]);
+
+    let check = repo.rtrim(&["--check", "--list-files"]);
+    assert_eq!(check.status.code(), Some(1), "{}", stderr(&check));
+    assert_eq!(stdout(&check), "a.txt\nb.txt\n");
+    assert_eq!(stderr(&check), "");
+
+    let output = repo.rtrim(&["--list-files", "-z"]);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "a.txt\0b.txt\0");
+    assert_eq!(stderr(&output), "");
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::{stderr, Repo};
+
+#[test]
+fn latin1_patches_are_trimmed() {
+    let repo = Repo::new();
+    repo.write("a.txt", b"caf\xe9 \nna\xefve\n");
+    let patch = repo.sibling("latin1.patch");
+    std::fs::write(&patch, b"--- a/a.txt\n+++ b/a.txt\n@@ -0,0 +1,2 @@\n+caf\xe9 \n+na\xefve\n").unwrap();
+
+    let output = repo.rtrim(&["--patch", patch.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.read("a.txt"), b"caf\xe9\nna\xefve\n");
+}
+
+#[test]
+fn malformed_hunk_headers_are_rejected() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a \n");
+    let patch = repo.sibling("bad.patch");
+    std::fs::write(&patch, "--- a/a.txt\n+++ b/a.txt\n@@ -1 +1,x @@\n+a \n").unwrap();
+
+    let output = repo.rtrim(&["--patch", patch.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "error invalid patch: line 3: malformed hunk header: @@ -1 +1,x @@\n");
+    assert_eq!(repo.read("a.txt"), b"a \n");
+}
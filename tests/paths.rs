@@ -0,0 +1,82 @@
+mod common;
+
+use common::{stderr, stdout, Repo};
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_paths_are_trimmed() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let repo = Repo::new();
+    let name = OsStr::from_bytes(b"caf\xe9.txt");
+    repo.write(name, "a \n");
+    repo.git(&["add", "-A"]);
+
+    let check = repo.rtrim(&["--check"]);
+    assert_eq!(check.status.code(), Some(1), "{}", stderr(&check));
+    assert!(stdout(&check).contains(r#""caf\351.txt""#), "{}", stdout(&check));
+
+    let sarif = repo.rtrim(&["--check", "--format", "sarif"]);
+    let sarif: serde_json::Value = serde_json::from_slice(&sarif.stdout).unwrap();
+    let location = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "caf%E9.txt");
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.read(name), b"a\n");
+    // the trimmed file was staged, `git diff` fails with changes left in the working tree
+    repo.git(&["diff", "--quiet"]);
+}
+
+#[test]
+fn quoted_paths_are_trimmed() {
+    let repo = Repo::new();
+    repo.write("\"q\".txt", "a \n");
+    repo.git(&["add", "-A"]);
+
+    let diff = repo.rtrim(&["--diff"]);
+    assert_eq!(diff.status.code(), Some(1), "{}", stderr(&diff));
+    assert!(stdout(&diff).contains(r#"--- "a/\"q\".txt""#), "{}", stdout(&diff));
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("\"q\".txt"), b"a\n");
+}
+
+/// The index has the name precomposed while the file system stores it decomposed, like core.precomposeunicode
+/// on macOS.
+#[test]
+fn decomposed_working_tree_names_are_found() {
+    let repo = Repo::new();
+    let (nfc, nfd) = ("caf\u{e9}.txt", "cafe\u{301}.txt");
+    repo.write(nfd, "a \n");
+    let blob = repo.git(&["hash-object", "-w", nfd]);
+    repo.git(&["update-index", "--add", "--cacheinfo", &format!("100644,{},{}", blob.trim(), nfc)]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.read(nfd), b"a\n");
+    assert_eq!(repo.staged(nfc), b"a\n");
+    assert!(!repo.path().join(nfc).exists());
+}
+
+#[test]
+fn machine_readable_reports_use_the_real_path() {
+    let repo = Repo::new();
+    repo.write("new\nline.txt", "a \n");
+    repo.git(&["add", "-A"]);
+
+    let json = repo.rtrim(&["--check", "--format", "json"]);
+    let report: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(report["files"][0]["path"], "new\nline.txt");
+
+    let list = repo.rtrim(&["--check", "--list-files", "-z"]);
+    assert_eq!(stdout(&list), "new\nline.txt\0");
+
+    let text = repo.rtrim(&["--check"]);
+    assert!(stdout(&text).starts_with(r#""new\nline.txt":1:2: "#), "{}", stdout(&text));
+}
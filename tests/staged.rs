@@ -0,0 +1,317 @@
+mod common;
+
+use common::{stderr, stdout, Repo};
+
+#[test]
+fn only_added_lines_are_trimmed() {
+    let repo = Repo::new();
+    repo.write("a.txt", "context  \nb\n");
+    repo.commit_all();
+    repo.write("a.txt", "context  \nb  \nc\t\n");
+    repo.git(&["add", "a.txt"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.read("a.txt"), b"context  \nb\nc\n");
+    assert_eq!(repo.staged("a.txt"), b"context  \nb\nc\n");
+}
+
+#[test]
+fn nothing_to_trim_exits_zero() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    repo.git(&["add", "a.txt"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(repo.staged("a.txt"), b"a\n");
+}
+
+#[test]
+fn git_errors_exit_three() {
+    let repo = Repo::new();
+
+    let output = repo.rtrim_in(&repo.sibling("home"), &[]);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).starts_with("error could not find repository"), "{}", stderr(&output));
+}
+
+#[test]
+fn check_leaves_the_files_alone() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a \n");
+    repo.git(&["add", "a.txt"]);
+
+    let output = repo.rtrim(&["--check"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("a.txt"), "{}", stdout(&output));
+    assert_eq!(repo.read("a.txt"), b"a \n");
+    assert_eq!(repo.staged("a.txt"), b"a \n");
+}
+
+#[test]
+fn renamed_and_edited_files_are_trimmed() {
+    let repo = Repo::new();
+    repo.write("old.txt", "one\ntwo\nthree\nfour\n");
+    repo.commit_all();
+    repo.git(&["mv", "old.txt", "new.txt"]);
+    repo.write("new.txt", "one\ntwo\nthree\nfour\nfive \n");
+    repo.git(&["add", "new.txt"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("new.txt"), b"one\ntwo\nthree\nfour\nfive\n");
+}
+
+#[test]
+fn deleted_files_are_skipped() {
+    let repo = Repo::new();
+    repo.write("gone.txt", "gone \n");
+    repo.write("a.txt", "a\n");
+    repo.commit_all();
+    repo.git(&["rm", "-q", "gone.txt"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(!repo.path().join("gone.txt").exists());
+}
+
+#[test]
+fn pathspecs_are_relative_to_the_current_directory() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a \n");
+    repo.write("sub/a.txt", "a \n");
+    repo.git(&["add", "-A"]);
+
+    let output = repo.rtrim_in(&repo.path().join("sub"), &["a.txt"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("sub/a.txt"), b"a\n");
+    assert_eq!(repo.staged("a.txt"), b"a \n");
+}
+
+#[test]
+fn pathspec_magic_excludes_files() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a \n");
+    repo.write("b.txt", "b \n");
+    repo.git(&["add", "-A"]);
+
+    let output = repo.rtrim(&["--", ".", ":!b.txt"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("a.txt"), b"a\n");
+    assert_eq!(repo.staged("b.txt"), b"b \n");
+}
+
+#[test]
+fn glob_pathspec_magic_matches_directories() {
+    let repo = Repo::new();
+    for path in ["src/a.rs", "src/deep/er/b.rs", "c.rs", "src/d.txt"] {
+        repo.write(path, "a \n");
+    }
+    repo.git(&["add", "-A"]);
+
+    let output = repo.rtrim(&["--", ":(glob)src/**/*.rs"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("src/a.rs"), b"a\n");
+    assert_eq!(repo.staged("src/deep/er/b.rs"), b"a\n");
+    assert_eq!(repo.staged("c.rs"), b"a \n");
+    assert_eq!(repo.staged("src/d.txt"), b"a \n");
+}
+
+#[test]
+fn icase_pathspec_magic_ignores_case() {
+    let repo = Repo::new();
+    repo.write("Docs/README.MD", "a \n");
+    repo.write("other.md", "a \n");
+    repo.git(&["add", "-A"]);
+
+    let output = repo.rtrim(&["--", ":(icase)docs/readme.md"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("Docs/README.MD"), b"a\n");
+    assert_eq!(repo.staged("other.md"), b"a \n");
+}
+
+#[test]
+fn literal_pathspec_magic_takes_wildcards_literally() {
+    let repo = Repo::new();
+    repo.write("a*.txt", "a \n");
+    repo.write("ab.txt", "a \n");
+    repo.git(&["add", "-A"]);
+
+    let output = repo.rtrim(&["--", ":(literal)a*.txt"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("a*.txt"), b"a\n");
+    assert_eq!(repo.staged("ab.txt"), b"a \n");
+}
+
+#[test]
+fn combined_pathspec_magic() {
+    let repo = Repo::new();
+    repo.write("A.txt", "a \n");
+    repo.write("b.txt", "b \n");
+    repo.git(&["add", "-A"]);
+
+    let output = repo.rtrim(&["--", ".", ":(exclude,icase)a.txt"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("A.txt"), b"a \n");
+    assert_eq!(repo.staged("b.txt"), b"b\n");
+}
+
+#[test]
+fn paths_starting_with_a_dash_follow_the_separator() {
+    let repo = Repo::new();
+    repo.write("-a.txt", "a \n");
+    repo.write("b.txt", "b \n");
+    repo.git(&["add", "-A"]);
+
+    let output = repo.rtrim(&["--", "-a.txt"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("-a.txt"), b"a\n");
+    assert_eq!(repo.staged("b.txt"), b"b \n");
+}
+
+#[cfg(unix)]
+#[test]
+fn executable_bit_is_kept() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = Repo::new();
+    repo.write("run.sh", "echo a \n");
+    std::fs::set_permissions(repo.path().join("run.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    repo.git(&["add", "run.sh"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("run.sh"), b"echo a\n");
+    assert!(repo.git(&["ls-files", "-s", "run.sh"]).starts_with("100755"));
+    let mode = std::fs::metadata(repo.path().join("run.sh")).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_not_rewritten() {
+    let repo = Repo::new();
+    repo.write("target.txt", "a \n");
+    repo.commit_all();
+    std::os::unix::fs::symlink("target.txt ", repo.path().join("link")).unwrap();
+    repo.git(&["add", "link"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(repo.staged("link"), b"target.txt ");
+    assert!(repo.path().join("link").symlink_metadata().unwrap().file_type().is_symlink());
+    assert_eq!(repo.read("target.txt"), b"a \n");
+}
+
+#[test]
+fn submodules_are_skipped() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    repo.commit_all();
+    let head = repo.git(&["rev-parse", "HEAD"]);
+    repo.git(&["update-index", "--add", "--cacheinfo", &format!("160000,{},sub", head.trim())]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(repo.git(&["ls-files", "-s", "sub"]).starts_with("160000"));
+}
+
+#[test]
+fn binary_files_are_skipped() {
+    let repo = Repo::new();
+    repo.write("a.bin", b"a \x00b \n");
+    repo.git(&["add", "a.bin"]);
+
+    let output = repo.rtrim(&["--verbose"]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stderr(&output).contains("a.bin: skipped as binary"), "{}", stderr(&output));
+    assert_eq!(repo.staged("a.bin"), b"a \x00b \n");
+}
+
+#[test]
+fn patch_files_are_skipped() {
+    let repo = Repo::new();
+    repo.write("fix.patch", "@@ -1 +1 @@\n context \n+added \n");
+    repo.git(&["add", "fix.patch"]);
+
+    let output = repo.rtrim(&[]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(repo.staged("fix.patch"), b"@@ -1 +1 @@\n context \n+added \n");
+}
+
+#[test]
+fn partially_staged_files_are_refused() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    repo.commit_all();
+    repo.write("a.txt", "a\nb \n");
+    repo.git(&["add", "a.txt"]);
+    repo.write("a.txt", "a\nb \nc\n");
+
+    let output = repo.rtrim(&[]);
+
+    assert_ne!(output.status.code(), Some(0));
+    assert_eq!(repo.read("a.txt"), b"a\nb \nc\n");
+    assert_eq!(repo.staged("a.txt"), b"a\nb \n");
+}
+
+#[test]
+fn force_dirty_counts_the_index_and_the_working_tree() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a\n");
+    repo.commit_all();
+    repo.write("a.txt", "a \nb\n");
+    repo.git(&["add", "a.txt"]);
+    repo.write("a.txt", "a \nb\nc\n");
+
+    let output = repo.rtrim(&["--force-dirty", "--format", "json"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["files_modified"], 1);
+    assert_eq!(report["lines_trimmed"], 2);
+    assert_eq!(report["bytes_removed"], 2);
+    assert_eq!(repo.staged("a.txt"), b"a\nb\n");
+    assert_eq!(repo.read("a.txt"), b"a\nb\nc\n");
+}
+
+#[test]
+fn failed_backup_leaves_no_temp_file() {
+    let repo = Repo::new();
+    repo.write("a.txt", "a \n");
+    repo.git(&["add", "a.txt"]);
+    std::fs::create_dir(repo.path().join("a.txt.orig")).unwrap();
+
+    let failed = repo.rtrim(&["--backup"]);
+
+    assert_eq!(failed.status.code(), Some(4), "{}", stderr(&failed));
+    assert_eq!(repo.read("a.txt"), b"a \n");
+    let entries: Vec<_> = std::fs::read_dir(repo.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(entries.len(), 3, "{:?}", entries);
+
+    std::fs::remove_dir(repo.path().join("a.txt.orig")).unwrap();
+    let output = repo.rtrim(&["--backup"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(repo.staged("a.txt"), b"a\n");
+    assert_eq!(repo.read("a.txt.orig"), b"a \n");
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use std::io::{BufRead, BufReader};
+use std::time::{Duration, Instant};
+
+use common::Repo;
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_not_replaced() {
+    let repo = Repo::new();
+    std::fs::write(repo.sibling("target.txt"), "a \n").unwrap();
+    std::os::unix::fs::symlink(repo.sibling("target.txt"), repo.path().join("link")).unwrap();
+    repo.write("a.txt", "a\n");
+    repo.commit_all();
+
+    let mut watch = repo.spawn_rtrim(&["--verbose", "--whole-file", "watch", "--debounce", "50"]);
+    let mut stderr = BufReader::new(watch.stderr.take().unwrap()).lines();
+    assert!(stderr.next().unwrap().unwrap().contains("watching"));
+
+    std::fs::remove_file(repo.path().join("link")).unwrap();
+    std::os::unix::fs::symlink(repo.sibling("target.txt"), repo.path().join("link")).unwrap();
+    std::fs::write(repo.sibling("target.txt"), "a \nb \n").unwrap();
+    // a file that is trimmed after the link shows that its events were handled
+    std::thread::sleep(Duration::from_millis(300));
+    repo.write("a.txt", "a\nb \n");
+
+    let started = Instant::now();
+    while repo.read("a.txt") != b"a\nb\n" && started.elapsed() < Duration::from_secs(10) {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    watch.kill().unwrap();
+    _ = watch.wait();
+
+    assert_eq!(repo.read("a.txt"), b"a\nb\n");
+    assert!(repo.path().join("link").symlink_metadata().unwrap().file_type().is_symlink());
+    assert_eq!(std::fs::read(repo.sibling("target.txt")).unwrap(), b"a \nb \n");
+}